@@ -0,0 +1,36 @@
+CREATE MATERIALIZED VIEW queue_throughputs AS
+    SELECT
+        w.world_id AS world_id,
+        cast(SUM(s.cleared) / NULLIF(SUM(s.minutes), 0) as double precision) AS throughput,
+        COUNT(*) AS sample_count,
+        MAX(s.time) AS time
+    FROM worlds w
+    CROSS JOIN LATERAL (
+        SELECT
+            r.end_time AS time,
+            cast(p.position as double precision) AS cleared,
+            EXTRACT(EPOCH FROM (r.end_time - p.time)) / 60 AS minutes
+        FROM (
+            SELECT id, end_time
+            FROM recaps r
+            WHERE r.world_id = w.world_id
+            AND r.successful
+            AND NOT r.reentered
+            AND r.start_time > (now() AT TIME ZONE 'UTC') - interval '6 hours'
+            ORDER BY r.start_time DESC
+            LIMIT 25
+        ) r
+        CROSS JOIN LATERAL (
+            SELECT time, position
+            FROM recap_positions p
+            WHERE p.recap_id = r.id
+            ORDER BY p.time ASC
+            LIMIT 1
+        ) p
+        WHERE p.position > 0
+        AND r.end_time > p.time
+    ) s
+    GROUP BY w.world_id
+    ORDER BY w.world_id;
+
+CREATE UNIQUE INDEX ON queue_throughputs(world_id);
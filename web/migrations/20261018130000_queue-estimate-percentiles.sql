@@ -0,0 +1,105 @@
+DROP MATERIALIZED VIEW world_summary;
+DROP MATERIALIZED VIEW queue_estimates;
+
+--
+
+CREATE MATERIALIZED VIEW queue_estimates AS
+    SELECT 
+        w.world_id as world_id,
+        cast(COALESCE(EXTRACT(EPOCH FROM (r.end_time - p.time)), 0) as double precision) as duration,
+        q.size as size,
+        q.time as time,
+        cast(COALESCE(s.duration_p50, 0) as double precision) as duration_p50,
+        cast(COALESCE(s.duration_p90, 0) as double precision) as duration_p90,
+        s.sample_count as sample_count
+    FROM worlds w
+    CROSS JOIN LATERAL (
+        SELECT id, end_time
+        FROM recaps r
+        WHERE r.world_id = w.world_id
+        AND r.successful
+        AND NOT r.reentered
+        ORDER BY r.start_time DESC
+        LIMIT 1
+    ) r
+    CROSS JOIN LATERAL (
+        SELECT min(time) as time
+        FROM recap_positions p
+        WHERE p.recap_id = r.id
+    ) p
+    CROSS JOIN LATERAL (
+        SELECT size, time
+        FROM queue_sizes q
+        WHERE q.world_id = w.world_id
+    ) q
+    CROSS JOIN LATERAL (
+        SELECT
+            percentile_cont(0.5) WITHIN GROUP (ORDER BY d.duration) as duration_p50,
+            percentile_cont(0.9) WITHIN GROUP (ORDER BY d.duration) as duration_p90,
+            COUNT(*) as sample_count
+        FROM (
+            SELECT EXTRACT(EPOCH FROM (wr.end_time - wp.time)) as duration
+            FROM recaps wr
+            CROSS JOIN LATERAL (
+                SELECT min(time) as time
+                FROM recap_positions p
+                WHERE p.recap_id = wr.id
+            ) wp
+            WHERE wr.world_id = w.world_id
+            AND wr.successful
+            AND NOT wr.reentered
+            AND wr.start_time > (now() AT TIME ZONE 'UTC') - interval '2 hours'
+            AND wp.time IS NOT NULL
+        ) d
+    ) s
+    ORDER BY w.world_id;
+
+CREATE UNIQUE INDEX ON queue_estimates(world_id);
+
+--
+
+CREATE MATERIALIZED VIEW world_summary AS
+    SELECT
+    w.world_id,
+    w.world_name,
+    w.datacenter_id,
+    w.datacenter_name,
+    w.region_id,
+    w.region_abbreviation,
+    w.region_name,
+    ws.status,
+    ws.category,
+    ws.can_create,
+    ts.prohibit,
+    qe.time,
+    qe.size,
+    qe.duration,
+    qe.duration_p50,
+    qe.duration_p90,
+    qe.sample_count
+    FROM
+    worlds w
+    CROSS JOIN LATERAL (
+        SELECT prohibit
+        FROM travel_states t
+        WHERE t.world_id = w.world_id
+        ORDER BY t.time DESC
+        LIMIT 1
+    ) ts
+    CROSS JOIN LATERAL (
+        SELECT status, category, can_create
+        FROM world_statuses t
+        WHERE t.world_id = w.world_id
+        ORDER BY t.time DESC
+        LIMIT 1
+    ) ws
+    INNER JOIN (
+        SELECT
+        *
+        FROM
+        queue_estimates
+    ) qe ON w.world_id = qe.world_id
+    WHERE
+    w.hidden = FALSE;
+    
+CREATE UNIQUE INDEX ON world_summary(world_id);
@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS installs
+(
+    user_id         UUID        PRIMARY KEY,
+    secret_hash     BYTEA       NOT NULL,
+    created_at      TIMESTAMP   NOT NULL,
+    rotated_at      TIMESTAMP   NOT NULL
+);
@@ -0,0 +1,20 @@
+CREATE MATERIALIZED VIEW client_version_stats AS
+    SELECT
+        date_trunc('day', v.start_time) AS day,
+        v.source AS source,
+        COALESCE(split_part(v.client_version, '-', 1), 'unknown') AS version,
+        COALESCE(NULLIF(split_part(v.client_version, '-', 2), ''), 'unknown') AS configuration,
+        COUNT(DISTINCT v.user_id) AS user_count,
+        COUNT(*) AS recap_count
+    FROM (
+        SELECT 'login' AS source, user_id, start_time, client_version
+        FROM recaps
+        UNION ALL
+        SELECT 'duty' AS source, user_id, start_time, client_version
+        FROM duty_recaps
+    ) v
+    WHERE v.start_time > (now() AT TIME ZONE 'UTC') - interval '90 days'
+    GROUP BY 1, 2, 3, 4
+    ORDER BY 1, 2, 3, 4;
+
+CREATE UNIQUE INDEX ON client_version_stats(day, source, version, configuration);
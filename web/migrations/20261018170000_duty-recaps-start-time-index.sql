@@ -0,0 +1 @@
+CREATE INDEX IF NOT EXISTS duty_recaps_start_time_index ON duty_recaps (start_time ASC);
@@ -0,0 +1,28 @@
+CREATE MATERIALIZED VIEW content_estimates AS
+    SELECT
+        w.datacenter_id as datacenter_id,
+        cast((r.queued_flags >> 9) & 15 as smallint) as languages,
+        r.queued_content[1] as content_id,
+        j.role as role,
+        max(p.time) as time,
+        cast(percentile_cont(0.5) WITHIN GROUP (ORDER BY EXTRACT(EPOCH FROM (p.time - r.start_time))) as double precision) as duration_p50,
+        cast(percentile_cont(0.9) WITHIN GROUP (ORDER BY EXTRACT(EPOCH FROM (p.time - r.start_time))) as double precision) as duration_p90,
+        COUNT(*) as sample_count
+    FROM duty_recaps r
+    JOIN worlds w ON w.world_id = r.world_id
+    JOIN jobs j ON j.id = r.queued_job
+    CROSS JOIN LATERAL (
+        SELECT min(time) as time
+        FROM duty_pops p
+        WHERE p.recap_id = r.id
+    ) p
+    -- Only single duty queues, since the wait for several duties at once isn't comparable
+    WHERE cardinality(r.queued_content) = 1
+    AND r.party_members IS NULL
+    AND j.role IS NOT NULL
+    AND p.time IS NOT NULL
+    AND r.start_time > (now() AT TIME ZONE 'UTC') - interval '30 days'
+    AND NOT EXISTS (SELECT 1 FROM duty_recap_quarantine q WHERE q.recap_id = r.id)
+    GROUP BY 1, 2, 3, 4;
+
+CREATE UNIQUE INDEX ON content_estimates(datacenter_id, languages, content_id, role);
@@ -0,0 +1,78 @@
+-- Roles each recap's party brought. What's still needed depends on the queued content.
+CREATE TABLE IF NOT EXISTS duty_party_compositions
+(
+    recap_id        UUID        PRIMARY KEY REFERENCES duty_recaps ON DELETE CASCADE,
+    member_count    SMALLINT    NOT NULL,
+    tanks           SMALLINT    NOT NULL,
+    healers         SMALLINT    NOT NULL,
+    dps             SMALLINT    NOT NULL
+);
+
+-- Backfill from existing recaps
+INSERT INTO duty_party_compositions
+    SELECT
+        r.id,
+        cast(c.members as smallint),
+        cast(c.tanks as smallint),
+        cast(c.healers as smallint),
+        cast(c.dps as smallint)
+    FROM duty_recaps r
+    CROSS JOIN LATERAL (
+        SELECT
+            COUNT(*) as members,
+            COUNT(*) FILTER (WHERE j.role = 'tank') as tanks,
+            COUNT(*) FILTER (WHERE j.role = 'healer') as healers,
+            COUNT(*) FILTER (WHERE j.role = 'dps') as dps,
+            bool_and(j.role IS NOT NULL) as is_valid
+        FROM unnest(r.party_members) m
+        LEFT JOIN jobs j ON j.id = m.job
+    ) c
+    WHERE r.party_members IS NOT NULL
+    AND c.members > 0
+    AND c.members <= 8
+    AND c.is_valid
+ON CONFLICT DO NOTHING;
+
+-- Role slots per party for each roulette (content_id = 0) and duty (roulette_id = 0), upserted from game data on startup
+CREATE TABLE IF NOT EXISTS content_party_roles
+(
+    roulette_id     SMALLINT    NOT NULL,
+    content_id      SMALLINT    NOT NULL,
+    tanks           SMALLINT    NOT NULL,
+    healers         SMALLINT    NOT NULL,
+    dps             SMALLINT    NOT NULL,
+
+    PRIMARY KEY (roulette_id, content_id)
+);
+
+CREATE MATERIALIZED VIEW party_estimates AS
+    SELECT
+        w.datacenter_id as datacenter_id,
+        cast((r.queued_flags >> 9) & 15 as smallint) as languages,
+        COALESCE(r.queued_roulette, 0::smallint) as roulette_id,
+        COALESCE(r.queued_content[1], 0::smallint) as content_id,
+        cast(GREATEST(s.tanks - c.tanks, 0) as smallint) as tanks_needed,
+        cast(GREATEST(s.healers - c.healers, 0) as smallint) as healers_needed,
+        cast(GREATEST(s.dps - c.dps, 0) as smallint) as dps_needed,
+        max(p.time) as time,
+        cast(percentile_cont(0.5) WITHIN GROUP (ORDER BY EXTRACT(EPOCH FROM (p.time - r.start_time))) as double precision) as duration_p50,
+        cast(percentile_cont(0.9) WITHIN GROUP (ORDER BY EXTRACT(EPOCH FROM (p.time - r.start_time))) as double precision) as duration_p90,
+        COUNT(*) as sample_count
+    FROM duty_recaps r
+    JOIN duty_party_compositions c ON c.recap_id = r.id
+    JOIN content_party_roles s
+        ON (r.queued_roulette IS NOT NULL AND s.roulette_id = r.queued_roulette AND s.content_id = 0)
+        OR (r.queued_roulette IS NULL AND s.roulette_id = 0 AND s.content_id = r.queued_content[1])
+    JOIN worlds w ON w.world_id = r.world_id
+    CROSS JOIN LATERAL (
+        SELECT min(time) as time
+        FROM duty_pops p
+        WHERE p.recap_id = r.id
+    ) p
+    WHERE (r.queued_roulette IS NOT NULL OR cardinality(r.queued_content) = 1)
+    AND p.time IS NOT NULL
+    AND r.start_time > (now() AT TIME ZONE 'UTC') - interval '30 days'
+    AND NOT EXISTS (SELECT 1 FROM duty_recap_quarantine q WHERE q.recap_id = r.id)
+    GROUP BY 1, 2, 3, 4, 5, 6, 7;
+
+CREATE UNIQUE INDEX ON party_estimates(datacenter_id, languages, roulette_id, content_id, tanks_needed, healers_needed, dps_needed);
@@ -0,0 +1,53 @@
+CREATE INDEX IF NOT EXISTS duty_updates_time_index ON duty_updates (time DESC);
+
+-- roulette_sizes only keeps the latest wait per roulette, so anything older
+-- than a day is too stale to compare against
+CREATE MATERIALIZED VIEW role_demand AS
+    WITH waits AS (
+        SELECT
+            datacenter_id, role,
+            wait_time / NULLIF(AVG(wait_time) OVER w, 0) AS relative_wait,
+            COUNT(*) OVER w AS role_count
+        FROM roulette_sizes
+        WHERE wait_time IS NOT NULL
+        AND wait_time_time >= (now() AT TIME ZONE 'UTC') - interval '1 day'
+        WINDOW w AS (PARTITION BY datacenter_id, languages, roulette_id)
+    ),
+    wait_index AS (
+        SELECT datacenter_id, role, AVG(relative_wait) AS relative_wait, COUNT(*) AS sample_count
+        FROM waits
+        -- A roulette with only one role reported can't be compared
+        WHERE role_count > 1
+        GROUP BY 1, 2
+    ),
+    fill_index AS (
+        SELECT
+            w.datacenter_id, f.role,
+            AVG((f.param).found::double precision / (f.param).needed) AS fill_rate,
+            COUNT(*) AS sample_count
+        FROM duty_updates u
+        JOIN duty_recaps r ON r.id = u.recap_id
+        JOIN worlds w ON w.world_id = r.world_id
+        CROSS JOIN LATERAL (
+            VALUES
+                ('tank'::roulette_role, u.fill_params[1]),
+                ('healer'::roulette_role, u.fill_params[2]),
+                ('dps'::roulette_role, u.fill_params[3])
+        ) f(role, param)
+        WHERE u.update_type = 'thd'
+        AND u.time >= (now() AT TIME ZONE 'UTC') - interval '1 day'
+        AND cardinality(u.fill_params) = 3
+        AND (f.param).needed > 0
+        GROUP BY 1, 2
+    )
+    SELECT
+        COALESCE(wi.datacenter_id, fi.datacenter_id) AS datacenter_id,
+        COALESCE(wi.role, fi.role) AS role,
+        wi.relative_wait AS relative_wait,
+        wi.sample_count AS wait_sample_count,
+        fi.fill_rate AS fill_rate,
+        fi.sample_count AS fill_sample_count
+    FROM wait_index wi
+    FULL OUTER JOIN fill_index fi ON fi.datacenter_id = wi.datacenter_id AND fi.role = wi.role;
+
+CREATE UNIQUE INDEX ON role_demand(datacenter_id, role);
@@ -0,0 +1,2 @@
+CREATE INDEX IF NOT EXISTS recaps_user_id_start_time_index ON recaps (user_id, start_time DESC);
+CREATE INDEX IF NOT EXISTS duty_recaps_user_id_start_time_index ON duty_recaps (user_id, start_time DESC);
@@ -0,0 +1,40 @@
+CREATE TYPE subscription_endpoint AS ENUM ('datacenter', 'world');
+-- Cancelled subscriptions were removed while their reminder was being delivered. They're kept
+-- so the in-flight delivery can still be logged against them.
+CREATE TYPE subscription_state AS ENUM ('active', 'pending', 'delivered', 'disabled', 'cancelled');
+
+CREATE TABLE IF NOT EXISTS travel_subscriptions
+(
+    id                  UUID                    PRIMARY KEY,
+    endpoint_type       subscription_endpoint   NOT NULL,
+    endpoint_id         SMALLINT                NOT NULL,
+    discord_user_id     BIGINT                  NOT NULL,
+    created_at          TIMESTAMP               NOT NULL DEFAULT (NOW() AT TIME ZONE 'UTC'),
+
+    state               subscription_state      NOT NULL DEFAULT 'active',
+    attempts            SMALLINT                NOT NULL DEFAULT 0,
+    next_attempt_at     TIMESTAMP,
+    last_error          VARCHAR,
+    delivered_at        TIMESTAMP,
+    disabled_reason     VARCHAR
+);
+
+-- Only one live subscription per user and endpoint. Finished ones are kept around for auditing.
+CREATE UNIQUE INDEX IF NOT EXISTS travel_subscriptions_live_idx
+    ON travel_subscriptions (endpoint_type, endpoint_id, discord_user_id)
+    WHERE state IN ('active', 'pending');
+
+CREATE INDEX IF NOT EXISTS travel_subscriptions_retry_idx
+    ON travel_subscriptions (next_attempt_at)
+    WHERE state = 'pending';
+
+CREATE TABLE IF NOT EXISTS travel_subscription_deliveries
+(
+    subscription_id     UUID        NOT NULL REFERENCES travel_subscriptions ON DELETE CASCADE,
+    attempt             SMALLINT    NOT NULL,
+    time                TIMESTAMP   NOT NULL,
+    success             BOOLEAN     NOT NULL,
+    error               VARCHAR,
+
+    PRIMARY KEY (subscription_id, attempt)
+);
@@ -0,0 +1,4 @@
+-- Lets users list and clear their own reminders
+CREATE INDEX IF NOT EXISTS travel_subscriptions_subscriber_idx
+    ON travel_subscriptions (discord_user_id)
+    WHERE state IN ('active', 'pending');
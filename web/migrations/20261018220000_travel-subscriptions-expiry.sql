@@ -0,0 +1,13 @@
+ALTER TYPE subscription_state ADD VALUE IF NOT EXISTS 'expired';
+
+ALTER TABLE travel_subscriptions
+    -- Persistent subscriptions go back to active after each reminder
+    ADD COLUMN IF NOT EXISTS persistent         BOOLEAN     NOT NULL DEFAULT FALSE,
+    -- Minimum time between reminders of a persistent subscription
+    ADD COLUMN IF NOT EXISTS cooldown_secs      INTEGER     NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS last_notified_at   TIMESTAMP,
+    ADD COLUMN IF NOT EXISTS expires_at         TIMESTAMP;
+
+CREATE INDEX IF NOT EXISTS travel_subscriptions_expiry_idx
+    ON travel_subscriptions (expires_at)
+    WHERE state = 'active' AND expires_at IS NOT NULL;
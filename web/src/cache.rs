@@ -4,9 +4,14 @@ use serde::Serialize;
 
 use crate::storage::redis::{client::RedisClient, utils::RedisKey};
 
-#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Serialize, Clone, PartialEq, Eq, Hash)]
 pub enum CacheKey {
     WorldSummary,
+    RouletteOutcomes {
+        datacenter_id: u16,
+        roulette_ids: Option<Vec<u8>>,
+        days: u32,
+    },
 }
 
 impl RedisKey for CacheKey {
@@ -1,3 +1,4 @@
+use crate::middleware::version::ClientVersion;
 use serde::Deserialize;
 use serenity::all::{ActivityData, ActivityType, ChannelId, GuildId, RoleId};
 
@@ -66,6 +67,55 @@ pub struct RedisConfig {
     pub url: String,
     pub namespace: String,
     pub cache_ttl_ms: u64,
+    #[serde(default = "RedisConfig::default_idempotency_ttl_ms")]
+    pub idempotency_ttl_ms: u64,
+}
+
+impl RedisConfig {
+    // Long enough to cover the plugin retrying a submission the next day
+    const fn default_idempotency_ttl_ms() -> u64 {
+        24 * 60 * 60 * 1000
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AuthConfig {
+    // Accept the old shared password from installs that haven't registered yet.
+    // The password is public, so this is off unless explicitly enabled.
+    pub allow_legacy_password: bool,
+    // Bearer token for the admin API; the admin API rejects everything if unset
+    pub admin_token: Option<String>,
+}
+
+// Accepts every version by default
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct ClientVersionConfig {
+    // Oldest plugin version that is accepted anywhere
+    pub minimum: Option<ClientVersion>,
+    // Stricter ranges for specific endpoints
+    pub endpoints: Vec<EndpointVersionRange>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EndpointVersionRange {
+    // Path prefix the range applies to (e.g. /api/v2/queue/duty/)
+    pub path: String,
+    pub minimum: Option<ClientVersion>,
+    pub maximum: Option<ClientVersion>,
+}
+
+impl ClientVersionConfig {
+    // Returns the strictest (minimum, maximum) range that applies to the path
+    pub fn range_for(&self, path: &str) -> (Option<ClientVersion>, Option<ClientVersion>) {
+        self.endpoints
+            .iter()
+            .filter(|e| path.starts_with(&e.path))
+            .fold((self.minimum, None), |(min, max), e| {
+                (min.max(e.minimum), max.into_iter().chain(e.maximum).min())
+            })
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -74,6 +124,10 @@ pub struct Config {
     pub metrics_server_addr: String,
     pub database_url: String,
     pub redis: RedisConfig,
+    #[serde(default)]
+    pub auth: AuthConfig,
+    #[serde(default)]
+    pub client_versions: ClientVersionConfig,
     pub max_connections_per_user: u32,
     pub discord: DiscordConfig,
     pub stasis: StasisConfig,
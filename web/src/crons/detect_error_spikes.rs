@@ -0,0 +1,112 @@
+use super::CronJob;
+use crate::{
+    await_cancellable,
+    discord::DiscordClient,
+    storage::{db, game::worlds},
+};
+use serenity::async_trait;
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+use time::OffsetDateTime;
+use tokio_util::sync::CancellationToken;
+
+// Window that is checked for a spike
+const RECENT_WINDOW: time::Duration = time::Duration::minutes(15);
+// Window (preceding the recent window) that the spike is compared against
+const BASELINE_WINDOW: time::Duration = time::Duration::hours(24);
+// Minimum number of errors in the recent window before anything is reported
+const MIN_RECENT_COUNT: i64 = 10;
+// How many times higher than the baseline rate the recent rate has to be
+const SPIKE_FACTOR: f64 = 4.0;
+// Minimum time between reports for the same datacenter and error code
+const REPORT_COOLDOWN: time::Duration = time::Duration::hours(1);
+
+pub struct DetectErrorSpikes {
+    client: DiscordClient,
+    last_reported: Mutex<HashMap<(u16, i32), OffsetDateTime>>,
+}
+
+impl DetectErrorSpikes {
+    pub fn new(client: DiscordClient) -> Self {
+        Self {
+            client,
+            last_reported: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl CronJob for DetectErrorSpikes {
+    const NAME: &'static str = "detect_error_spikes";
+    const PERIOD: Duration = Duration::from_secs(300);
+
+    async fn run(&self, stop_signal: CancellationToken) -> anyhow::Result<()> {
+        let now = OffsetDateTime::now_utc();
+        let recent_start = now - RECENT_WINDOW;
+        let baseline_start = recent_start - BASELINE_WINDOW;
+
+        let windows = await_cancellable!(
+            db::login::get_error_code_windows(
+                self.client.db(),
+                baseline_start.into(),
+                recent_start.into()
+            ),
+            stop_signal
+        );
+
+        // Spikes are only marked as reported once the message is sent, so
+        // failed sends are tried again on the next run
+        let spikes = {
+            let mut last_reported = self.last_reported.lock().unwrap();
+            last_reported.retain(|_, time| now - *time < REPORT_COOLDOWN);
+
+            windows
+                .into_iter()
+                .filter(|w| w.recent_count >= MIN_RECENT_COUNT)
+                .filter(|w| {
+                    // Scale the baseline down to the size of the recent window
+                    #[allow(clippy::cast_precision_loss)]
+                    let expected = w.baseline_count as f64 * (RECENT_WINDOW / BASELINE_WINDOW);
+                    #[allow(clippy::cast_precision_loss)]
+                    let recent = w.recent_count as f64;
+                    recent > expected * SPIKE_FACTOR
+                })
+                .filter(|w| !last_reported.contains_key(&(w.datacenter_id, w.error_code)))
+                .collect::<Vec<_>>()
+        };
+
+        let travel_data = worlds::get_data();
+        for spike in spikes {
+            let datacenter = travel_data
+                .get_datacenter_by_id(spike.datacenter_id)
+                .map_or_else(
+                    || format!("Datacenter {}", spike.datacenter_id),
+                    ToString::to_string,
+                );
+            log::warn!(
+                "Error {} spike on {}: {} recent, {} baseline",
+                spike.error_code,
+                datacenter,
+                spike.recent_count,
+                spike.baseline_count
+            );
+            await_cancellable!(
+                self.client.send_log_message(format!(
+                    "⚠️ Login error {} is spiking on {}: {} errors in the last {} minutes ({} in the previous {} hours)",
+                    spike.error_code,
+                    datacenter,
+                    spike.recent_count,
+                    RECENT_WINDOW.whole_minutes(),
+                    spike.baseline_count,
+                    BASELINE_WINDOW.whole_hours()
+                )),
+                stop_signal
+            );
+            self.last_reported
+                .lock()
+                .unwrap()
+                .insert((spike.datacenter_id, spike.error_code), now);
+        }
+
+        Ok(())
+    }
+}
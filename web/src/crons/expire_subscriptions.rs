@@ -0,0 +1,26 @@
+use super::CronJob;
+use crate::{await_cancellable, subscriptions::SubscriptionManager};
+use serenity::async_trait;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+pub struct ExpireSubscriptions {
+    subscriptions: SubscriptionManager,
+}
+
+impl ExpireSubscriptions {
+    pub fn new(subscriptions: SubscriptionManager) -> Self {
+        Self { subscriptions }
+    }
+}
+
+#[async_trait]
+impl CronJob for ExpireSubscriptions {
+    const NAME: &'static str = "expire_subscriptions";
+    const PERIOD: Duration = Duration::from_secs(60);
+
+    async fn run(&self, stop_signal: CancellationToken) -> anyhow::Result<()> {
+        await_cancellable!(self.subscriptions.expire_subscriptions(), stop_signal);
+        Ok(())
+    }
+}
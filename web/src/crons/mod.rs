@@ -1,3 +1,12 @@
+pub mod detect_error_spikes;
+pub use detect_error_spikes::DetectErrorSpikes;
+
+pub mod expire_subscriptions;
+pub use expire_subscriptions::ExpireSubscriptions;
+
+pub mod refresh_client_version_stats;
+pub use refresh_client_version_stats::RefreshClientVersionStats;
+
 pub mod refresh_materialized_views;
 pub use refresh_materialized_views::RefreshMaterializedViews;
 
@@ -7,6 +16,9 @@ pub use refresh_travel_states::RefreshTravelStates;
 pub mod refresh_world_statuses;
 pub use refresh_world_statuses::RefreshWorldStatuses;
 
+pub mod retry_subscriptions;
+pub use retry_subscriptions::RetrySubscriptions;
+
 pub mod update_activity;
 pub use update_activity::UpdateActivity;
 
@@ -0,0 +1,33 @@
+use super::CronJob;
+use crate::{await_cancellable, stopwatch::Stopwatch, storage::db};
+use serenity::async_trait;
+use sqlx::PgPool;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+pub struct RefreshClientVersionStats {
+    pool: PgPool,
+}
+
+impl RefreshClientVersionStats {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+// Scans 90 days of logins, so it runs apart from the per-minute estimate refreshes
+#[async_trait]
+impl CronJob for RefreshClientVersionStats {
+    const NAME: &'static str = "refresh_client_version_stats";
+    const PERIOD: Duration = Duration::from_secs(60 * 60);
+    const TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+    async fn run(&self, stop_signal: CancellationToken) -> anyhow::Result<()> {
+        let _s = Stopwatch::new("client_version_stats");
+        await_cancellable!(
+            db::client_versions::refresh_client_version_stats(&self.pool),
+            stop_signal
+        );
+        Ok(())
+    }
+}
@@ -26,6 +26,22 @@ impl CronJob for RefreshMaterializedViews {
             let _s = Stopwatch::new("queue_estimates");
             await_cancellable!(db::login::refresh_queue_estimates(pool), stop_signal);
         }
+        {
+            let _s = Stopwatch::new("queue_throughputs");
+            await_cancellable!(db::login::refresh_queue_throughputs(pool), stop_signal);
+        }
+        {
+            let _s = Stopwatch::new("content_estimates");
+            await_cancellable!(db::duty::refresh_content_estimates(pool), stop_signal);
+        }
+        {
+            let _s = Stopwatch::new("party_estimates");
+            await_cancellable!(db::duty::refresh_party_estimates(pool), stop_signal);
+        }
+        {
+            let _s = Stopwatch::new("role_demand");
+            await_cancellable!(db::duty::refresh_role_demand(pool), stop_signal);
+        }
         {
             let _s = Stopwatch::new("world_summaries");
             await_cancellable!(db::summary::refresh_world_summaries(pool), stop_signal);
@@ -206,6 +206,11 @@ impl CronJob for RefreshTravelStates {
 
         let travel_states: Vec<DCTravelWorldInfo> = travel_map.values().cloned().collect();
 
+        // Used to tell which endpoints just reopened, since persistent
+        // subscriptions are only reminded once per opening
+        let previous_states = db::travel::get_travel_states(&self.pool).await?;
+        let was_prohibited = |world_id: u16| previous_states.get(&world_id).copied();
+
         db::travel::add_travel_states(&self.pool, travel_states.clone(), travel_time.unwrap())
             .await?;
 
@@ -214,24 +219,36 @@ impl CronJob for RefreshTravelStates {
             if world.prohibit == 0 {
                 if let Some(world_param) = travel_params.get_world_by_id(world.id) {
                     if published_datacenters.insert(world_param.datacenter.id) {
+                        let dc_worlds = travel_params
+                            .worlds
+                            .iter()
+                            .filter(|w| w.datacenter.id == world_param.datacenter.id)
+                            .collect_vec();
+                        let reopened = dc_worlds
+                            .iter()
+                            .all(|w| was_prohibited(w.id).unwrap_or_default());
                         self.subscriptions
-                            .publish_endpoint(EndpointPublish::Datacenter {
-                                id: world_param.datacenter.id,
-                                data: &world_param.datacenter,
-                                worlds: travel_params
-                                    .worlds
-                                    .iter()
-                                    .filter(|w| w.datacenter.id == world_param.datacenter.id)
-                                    .map(|w| (w, travel_map.get(&w.id).unwrap().prohibit != 0))
-                                    .collect::<Vec<_>>(),
-                            })
+                            .publish_endpoint(
+                                EndpointPublish::Datacenter {
+                                    id: world_param.datacenter.id,
+                                    data: &world_param.datacenter,
+                                    worlds: dc_worlds
+                                        .into_iter()
+                                        .map(|w| (w, travel_map.get(&w.id).unwrap().prohibit != 0))
+                                        .collect::<Vec<_>>(),
+                                },
+                                reopened,
+                            )
                             .await?;
                     }
                     self.subscriptions
-                        .publish_endpoint(EndpointPublish::World {
-                            id: world.id,
-                            data: world_param,
-                        })
+                        .publish_endpoint(
+                            EndpointPublish::World {
+                                id: world.id,
+                                data: world_param,
+                            },
+                            was_prohibited(world.id).unwrap_or_default(),
+                        )
                         .await?;
                 }
             }
@@ -0,0 +1,26 @@
+use super::CronJob;
+use crate::{await_cancellable, subscriptions::SubscriptionManager};
+use serenity::async_trait;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+pub struct RetrySubscriptions {
+    subscriptions: SubscriptionManager,
+}
+
+impl RetrySubscriptions {
+    pub fn new(subscriptions: SubscriptionManager) -> Self {
+        Self { subscriptions }
+    }
+}
+
+#[async_trait]
+impl CronJob for RetrySubscriptions {
+    const NAME: &'static str = "retry_subscriptions";
+    const PERIOD: Duration = Duration::from_secs(30);
+
+    async fn run(&self, stop_signal: CancellationToken) -> anyhow::Result<()> {
+        await_cancellable!(self.subscriptions.retry_deliveries(), stop_signal);
+        Ok(())
+    }
+}
@@ -180,7 +180,10 @@ impl DiscordClient {
         &self.imp.config
     }
 
-    async fn send_log_message(&self, message: impl Into<String>) -> Result<(), serenity::Error> {
+    pub async fn send_log_message(
+        &self,
+        message: impl Into<String>,
+    ) -> Result<(), serenity::Error> {
         self.config()
             .log_channel_id
             .say(self.http(), message)
@@ -1,11 +1,14 @@
+use super::utils::create_client_version_embed;
 use super::Context;
 use super::Error;
+use crate::storage::db;
 use ::serenity::all::{
     CreateActionRow, CreateAllowedMentions, CreateInputText, CreateInteractionResponse,
     CreateMessage, CreateQuickModal, CreateSelectMenu, ReactionType, Role, RoleId,
 };
 use itertools::Itertools;
-use poise::serenity_prelude as serenity;
+use poise::{serenity_prelude as serenity, CreateReply};
+use time::OffsetDateTime;
 
 #[derive(Debug, poise::ChoiceParameter)]
 pub enum Subcommand {
@@ -13,6 +16,8 @@ pub enum Subcommand {
     CreateMessage,
     #[name = "Create role message"]
     CreateRoleMessage,
+    #[name = "Client versions"]
+    ClientVersions,
 }
 
 #[poise::command(
@@ -29,13 +34,14 @@ pub enum Subcommand {
 pub async fn admin(
     ctx: Context<'_>,
     subcommand: Subcommand,
-    #[channel_types("Text")] channel: serenity::GuildChannel,
+    #[channel_types("Text")] channel: Option<serenity::GuildChannel>,
 ) -> Result<(), Error> {
     let poise::Context::Application(ctx) = ctx else {
         return Err(Error::Admin);
     };
     match subcommand {
         Subcommand::CreateMessage => {
+            let channel = channel.ok_or(Error::Admin)?;
             let modal = CreateQuickModal::new("Message Content")
                 .timeout(std::time::Duration::from_secs(30))
                 .field(
@@ -66,6 +72,7 @@ pub async fn admin(
                 .await?;
         }
         Subcommand::CreateRoleMessage => {
+            let channel = channel.ok_or(Error::Admin)?;
             let modal = CreateQuickModal::new("Message Content")
                 .timeout(std::time::Duration::from_secs(3600))
                 .field(
@@ -144,6 +151,14 @@ pub async fn admin(
                 .create_response(ctx.http(), CreateInteractionResponse::Acknowledge)
                 .await?;
         }
+        Subcommand::ClientVersions => {
+            let since = OffsetDateTime::now_utc() - time::Duration::days(7);
+            let stats =
+                db::client_versions::get_client_version_stats(ctx.data().db(), since.into())
+                    .await?;
+            ctx.send(CreateReply::default().embed(create_client_version_embed(&stats)))
+                .await?;
+        }
     }
     Ok(())
 }
@@ -0,0 +1,167 @@
+use super::utils::{create_role_demand_embed, create_roulette_embed};
+use super::Context;
+use super::Error;
+use crate::{
+    models::duty::{QueueLanguage, RouletteRole},
+    storage::{
+        db,
+        game::{
+            content,
+            worlds::{self, Datacenter},
+        },
+    },
+};
+use itertools::Itertools;
+use poise::{serenity_prelude as serenity, CreateReply};
+
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+enum Language {
+    #[name = "Japanese"]
+    Jp,
+    #[name = "English"]
+    En,
+    #[name = "German"]
+    De,
+    #[name = "French"]
+    Fr,
+    #[name = "Japanese, English"]
+    JpEn,
+    #[name = "Japanese, German"]
+    JpDe,
+    #[name = "Japanese, French"]
+    JpFr,
+    #[name = "English, German"]
+    EnDe,
+    #[name = "English, French"]
+    EnFr,
+    #[name = "German, French"]
+    DeFr,
+    #[name = "Japanese, English, German"]
+    JpEnDe,
+    #[name = "Japanese, English, French"]
+    JpEnFr,
+    #[name = "Japanese, German, French"]
+    JpDeFr,
+    #[name = "English, German, French"]
+    EnDeFr,
+    #[name = "All languages"]
+    JpEnDeFr,
+}
+
+impl From<Language> for QueueLanguage {
+    fn from(value: Language) -> Self {
+        match value {
+            Language::Jp => QueueLanguage::Jp,
+            Language::En => QueueLanguage::En,
+            Language::De => QueueLanguage::De,
+            Language::Fr => QueueLanguage::Fr,
+            Language::JpEn => QueueLanguage::JpEn,
+            Language::JpDe => QueueLanguage::JpDe,
+            Language::JpFr => QueueLanguage::JpFr,
+            Language::EnDe => QueueLanguage::EnDe,
+            Language::EnFr => QueueLanguage::EnFr,
+            Language::DeFr => QueueLanguage::DeFr,
+            Language::JpEnDe => QueueLanguage::JpEnDe,
+            Language::JpEnFr => QueueLanguage::JpEnFr,
+            Language::JpDeFr => QueueLanguage::JpDeFr,
+            Language::EnDeFr => QueueLanguage::EnDeFr,
+            Language::JpEnDeFr => QueueLanguage::JpEnDeFr,
+        }
+    }
+}
+
+async fn autocomplete_roulette<'a>(
+    _ctx: Context<'_>,
+    query: &'a str,
+) -> impl Iterator<Item = serenity::AutocompleteChoice> + 'a {
+    let query = query.to_lowercase();
+    content::get_data()
+        .roulettes
+        .values()
+        .filter(move |r| r.name.to_lowercase().contains(&query))
+        .sorted_unstable_by_key(|r| r.id)
+        .take(25)
+        .map(|r| serenity::AutocompleteChoice::new(r.name.clone(), r.id))
+}
+
+#[poise::command(
+    slash_command,
+    install_context = "Guild|User",
+    interaction_context = "Guild|BotDm|PrivateChannel",
+    subcommands("roulette", "roles")
+)]
+#[allow(clippy::unused_async)]
+pub async fn duty(_: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Check roulette wait times for a datacenter
+#[poise::command(slash_command)]
+async fn roulette(
+    ctx: Context<'_>,
+    #[description = "Datacenter to check for"] datacenter: Datacenter,
+    #[description = "Role to check for"] role: Option<RouletteRole>,
+    #[description = "Languages that were queued for"] language: Option<Language>,
+    #[description = "Roulette to check for"]
+    #[autocomplete = "autocomplete_roulette"]
+    roulette: Option<u8>,
+) -> Result<(), Error> {
+    let client = ctx.data();
+    let db = client.db();
+    let datacenter = worlds::get_data()
+        .get_datacenter_by_id(datacenter.id)
+        .ok_or(Error::UnknownDatacenter)?;
+
+    let estimates = if let Some(language) = language {
+        db::duty::get_roulette_estimates_by_datacenter_id(db, datacenter.id, language.into())
+            .await?
+    } else {
+        // Without a language, show whichever queue reported most recently
+        db::duty::get_roulette_estimates(db)
+            .await?
+            .into_iter()
+            .filter(|e| e.datacenter_id == datacenter.id)
+            .into_group_map_by(|e| (e.roulette_id, e.role as u8))
+            .into_values()
+            .filter_map(|e| e.into_iter().max_by_key(|e| e.last_update))
+            .collect()
+    };
+
+    let estimates = estimates
+        .into_iter()
+        .filter(|e| role.is_none_or(|role| role == e.role))
+        .filter(|e| roulette.is_none_or(|roulette| roulette == e.roulette_id))
+        .collect();
+
+    let embed = create_roulette_embed(&datacenter.to_string(), estimates, &client.config().emotes);
+
+    ctx.send(CreateReply::default().reply(true).embed(embed))
+        .await?;
+
+    Ok(())
+}
+
+/// Check which roles are in demand for roulettes
+#[poise::command(slash_command)]
+async fn roles(
+    ctx: Context<'_>,
+    #[description = "Datacenter to check for"] datacenter: Datacenter,
+) -> Result<(), Error> {
+    let client = ctx.data();
+    let demand = db::duty::get_role_demand(client.db()).await?;
+    let datacenter = worlds::get_data()
+        .get_datacenter_by_id(datacenter.id)
+        .ok_or(Error::UnknownDatacenter)?;
+
+    let demand = demand
+        .into_iter()
+        .filter(|d| d.datacenter_id == datacenter.id)
+        .collect();
+
+    let embed = create_role_demand_embed(&datacenter.to_string(), demand, &client.config().emotes);
+
+    ctx.send(CreateReply::default().reply(true).embed(embed))
+        .await?;
+
+    Ok(())
+}
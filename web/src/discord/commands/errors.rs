@@ -0,0 +1,51 @@
+use super::utils::create_error_rate_embed;
+use super::Context;
+use super::Error;
+use crate::storage::{
+    db,
+    game::worlds::{self, Datacenter},
+};
+use poise::CreateReply;
+use time::OffsetDateTime;
+
+/// Check login error rates over the last hour
+#[poise::command(
+    slash_command,
+    install_context = "Guild|User",
+    interaction_context = "Guild|BotDm|PrivateChannel"
+)]
+pub async fn errors(
+    ctx: Context<'_>,
+    #[description = "Datacenter to check for"] datacenter: Option<Datacenter>,
+) -> Result<(), Error> {
+    let client = ctx.data();
+    let db = client.db();
+    let since = OffsetDateTime::now_utc() - time::Duration::hours(1);
+    let rates = db::login::get_datacenter_error_rates(db, since.into()).await?;
+    let travel_data = worlds::get_data();
+
+    let rates = rates
+        .into_iter()
+        .filter(|rate| {
+            datacenter
+                .as_ref()
+                .is_none_or(|datacenter| datacenter.id == rate.datacenter_id)
+        })
+        .map(|rate| {
+            travel_data
+                .get_datacenter_by_id(rate.datacenter_id)
+                .map(|v| (v, rate))
+                .ok_or(Error::UnknownDatacenter)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let embed = create_error_rate_embed(
+        &datacenter.map_or_else(|| "All Datacenters".to_string(), |dc| dc.to_string()),
+        rates,
+    );
+
+    ctx.send(CreateReply::default().reply(true).embed(embed))
+        .await?;
+
+    Ok(())
+}
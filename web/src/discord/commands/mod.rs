@@ -1,6 +1,8 @@
 use super::DiscordClient;
 
 mod admin;
+mod duty;
+mod errors;
 mod queue_times;
 mod stats;
 mod subscribe;
@@ -24,6 +26,8 @@ pub enum Error {
     Native(#[from] crate::natives::Error),
     #[error("Subscription error")]
     Subscription(#[from] crate::subscriptions::Error),
+    #[error("Image encoding error")]
+    Image(#[from] png::EncodingError),
     #[error("Unknown world")]
     UnknownWorld,
     #[error("Unknown datacenter")]
@@ -36,6 +40,8 @@ pub fn command_list() -> Vec<Command> {
     vec![
         travel::travel(),
         queue_times::queue_times(),
+        errors::errors(),
+        duty::duty(),
         subscribe::subscribe(),
         unsubscribe::unsubscribe(),
         stats::stats(),
@@ -1,18 +1,25 @@
 use super::utils::{autocomplete_world, create_queue_embed};
 use super::Context;
 use super::Error;
-use crate::storage::{
-    db,
-    game::worlds::{self, Datacenter},
+use crate::{
+    discord::utils::{COLOR_ERROR, COLOR_IN_QUEUE},
+    heatmap::render_heatmap,
+    models::login::HeatmapQuery,
+    storage::{
+        db,
+        game::worlds::{self, Datacenter},
+    },
 };
 use poise::CreateReply;
+use serenity::all::{CreateAttachment, CreateEmbed, CreateEmbedFooter};
+use time::OffsetDateTime;
 
 #[poise::command(
     slash_command,
     install_context = "Guild|User",
     interaction_context = "Guild|BotDm|PrivateChannel",
     rename = "queue",
-    subcommands("datacenter", "world")
+    subcommands("datacenter", "world", "heatmap")
 )]
 #[allow(clippy::unused_async)]
 pub async fn queue_times(_: Context<'_>) -> Result<(), Error> {
@@ -78,3 +85,66 @@ async fn world(
 
     Ok(())
 }
+
+/// See the best times to log in to a world
+#[poise::command(slash_command)]
+async fn heatmap(
+    ctx: Context<'_>,
+    #[description = "World to check for"]
+    #[autocomplete = "autocomplete_world"]
+    world: u16,
+    #[description = "Time zone to use (e.g. America/New_York)"] timezone: Option<String>,
+) -> Result<(), Error> {
+    let world = worlds::get_data()
+        .get_world_by_id(world)
+        .cloned()
+        .ok_or(Error::UnknownWorld)?;
+    let timezone = timezone.unwrap_or_else(|| HeatmapQuery::DEFAULT_TIMEZONE.to_string());
+
+    let client = ctx.data();
+    let db = client.db();
+    if !db::login::is_valid_timezone(db, &timezone).await? {
+        let embed = CreateEmbed::new()
+            .title("Unknown time zone")
+            .description(format!(
+                "`{timezone}` isn't a time zone I recognize. Try a name like `America/New_York` or `Europe/London`."
+            ))
+            .color(COLOR_ERROR);
+        ctx.send(
+            CreateReply::default()
+                .reply(true)
+                .embed(embed)
+                .ephemeral(true),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    ctx.defer().await?;
+
+    let since = OffsetDateTime::now_utc() - time::Duration::days(HeatmapQuery::DEFAULT_DAYS.into());
+    let heatmap = db::login::get_queue_heatmap(db, world.id, timezone, since.into()).await?;
+    let image = render_heatmap(&heatmap)?;
+
+    let embed = CreateEmbed::new()
+        .title(format!("Queue Heatmap for {world}"))
+        .description(format!(
+            "Median queue time by hour of the week over the last {} days, in {}.",
+            HeatmapQuery::DEFAULT_DAYS,
+            heatmap.timezone
+        ))
+        .image("attachment://heatmap.png")
+        .footer(CreateEmbedFooter::new("Generated"))
+        .timestamp(OffsetDateTime::now_utc())
+        .color(COLOR_IN_QUEUE);
+
+    ctx.send(
+        CreateReply::default()
+            .reply(true)
+            .embed(embed)
+            .attachment(CreateAttachment::bytes(image, "heatmap.png")),
+    )
+    .await?;
+
+    Ok(())
+}
@@ -7,17 +7,131 @@ use crate::{
         db,
         game::worlds::{self, Datacenter, World},
     },
-    subscriptions::{Endpoint, Subscriber},
+    subscriptions::{Endpoint, Subscriber, SubscriptionInfo, SubscriptionOptions},
 };
-use ::serenity::all::CreateEmbed;
+use ::serenity::all::{
+    ButtonStyle, CreateActionRow, CreateButton, CreateEmbed, CreateEmbedFooter,
+    CreateInteractionResponse, CreateInteractionResponseMessage, FormattedTimestamp,
+    FormattedTimestampStyle,
+};
+use itertools::Itertools;
 use poise::CreateReply;
+use std::collections::HashMap;
+use time::{Duration, OffsetDateTime};
+
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+enum ReminderRepeat {
+    #[name = "Only once"]
+    Once,
+    #[name = "Every time it opens"]
+    Always,
+    #[name = "Every time it opens, at most hourly"]
+    Hourly,
+    #[name = "Every time it opens, at most every 6 hours"]
+    SixHourly,
+    #[name = "Every time it opens, at most daily"]
+    Daily,
+}
+
+impl ReminderRepeat {
+    fn cooldown(self) -> Option<Duration> {
+        match self {
+            Self::Once => None,
+            Self::Always => Some(Duration::ZERO),
+            Self::Hourly => Some(Duration::hours(1)),
+            Self::SixHourly => Some(Duration::hours(6)),
+            Self::Daily => Some(Duration::days(1)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+enum ReminderExpiry {
+    #[name = "1 hour"]
+    OneHour,
+    #[name = "3 hours"]
+    ThreeHours,
+    #[name = "6 hours"]
+    SixHours,
+    #[name = "12 hours"]
+    TwelveHours,
+    #[name = "1 day"]
+    OneDay,
+    #[name = "3 days"]
+    ThreeDays,
+    #[name = "1 week"]
+    OneWeek,
+}
+
+impl ReminderExpiry {
+    fn duration(self) -> Duration {
+        match self {
+            Self::OneHour => Duration::hours(1),
+            Self::ThreeHours => Duration::hours(3),
+            Self::SixHours => Duration::hours(6),
+            Self::TwelveHours => Duration::hours(12),
+            Self::OneDay => Duration::days(1),
+            Self::ThreeDays => Duration::days(3),
+            Self::OneWeek => Duration::weeks(1),
+        }
+    }
+}
+
+fn create_options(
+    repeat: Option<ReminderRepeat>,
+    expires: Option<ReminderExpiry>,
+) -> SubscriptionOptions {
+    SubscriptionOptions {
+        repeat_cooldown: repeat.and_then(ReminderRepeat::cooldown),
+        expires_at: expires.map(|e| (OffsetDateTime::now_utc() + e.duration()).into()),
+    }
+}
+
+// Appended to the subscription confirmation
+fn describe_options(options: &SubscriptionOptions) -> String {
+    let mut ret = String::new();
+    match options.repeat_cooldown {
+        None => {}
+        Some(cooldown) if cooldown.is_zero() => {
+            ret.push_str(" You will be reminded every time it opens.");
+        }
+        Some(cooldown) => ret.push_str(&format!(
+            " You will be reminded every time it opens, at most once every {}.",
+            format_cooldown(cooldown)
+        )),
+    }
+    if let Some(expires_at) = options.expires_at {
+        ret.push_str(&format!(
+            " This reminder expires {}.",
+            FormattedTimestamp::new(
+                expires_at.0.into(),
+                Some(FormattedTimestampStyle::RelativeTime)
+            )
+        ));
+    }
+    ret
+}
+
+fn format_cooldown(cooldown: Duration) -> String {
+    if cooldown.whole_days() != 0 && cooldown.whole_hours() % 24 == 0 {
+        match cooldown.whole_days() {
+            1 => "day".to_string(),
+            days => format!("{days} days"),
+        }
+    } else {
+        match cooldown.whole_hours() {
+            1 => "hour".to_string(),
+            hours => format!("{hours} hours"),
+        }
+    }
+}
 
 #[poise::command(
     slash_command,
     install_context = "Guild|User",
     interaction_context = "Guild|BotDm|PrivateChannel",
     rename = "remind",
-    subcommands("datacenter", "world")
+    subcommands("datacenter", "world", "list")
 )]
 #[allow(clippy::unused_async)]
 pub async fn subscribe(_: Context<'_>) -> Result<(), Error> {
@@ -29,13 +143,16 @@ pub async fn subscribe(_: Context<'_>) -> Result<(), Error> {
 async fn datacenter(
     ctx: Context<'_>,
     #[description = "Datacenter to remind for"] datacenter: Datacenter,
+    #[description = "Whether to keep reminding after it opens"] repeat: Option<ReminderRepeat>,
+    #[description = "Give up on the reminder after this long"] expires: Option<ReminderExpiry>,
 ) -> Result<(), Error> {
-    subscribe_datacenter(ctx, datacenter, false).await
+    subscribe_datacenter(ctx, datacenter, create_options(repeat, expires), false).await
 }
 
 pub async fn subscribe_datacenter(
     ctx: Context<'_>,
     datacenter: Datacenter,
+    options: SubscriptionOptions,
     ephemeral: bool,
 ) -> Result<(), Error> {
     let client = ctx.data();
@@ -58,21 +175,30 @@ pub async fn subscribe_datacenter(
             })
             .collect::<Result<Vec<_>, _>>()?;
 
-        create_travel_embed(&datacenter.to_string(), worlds, &config.emotes)
-            .description("This datacenter is aleady open for travel.")
-            .color(COLOR_ERROR)
+        create_travel_embed(
+            &datacenter.to_string(),
+            worlds,
+            &HashMap::new(),
+            &config.emotes,
+        )
+        .description("This datacenter is aleady open for travel.")
+        .color(COLOR_ERROR)
     } else {
         let success = subscriptions
             .subscribe(
                 Endpoint::Datacenter(datacenter.id),
                 Subscriber::Discord(ctx.author().id.get()),
+                options,
             )
             .await?;
 
         if success {
             CreateEmbed::new()
                 .title(format!("Subscribed to {}", datacenter))
-                .description("You will be reminded when this datacenter is open for travel.")
+                .description(format!(
+                    "You will be reminded when this datacenter is open for travel.{}",
+                    describe_options(&options)
+                ))
                 .color(COLOR_SUCCESS)
         } else {
             CreateEmbed::new()
@@ -98,15 +224,22 @@ async fn world(
     #[description = "World to remind for"]
     #[autocomplete = "autocomplete_world"]
     world: u16,
+    #[description = "Whether to keep reminding after it opens"] repeat: Option<ReminderRepeat>,
+    #[description = "Give up on the reminder after this long"] expires: Option<ReminderExpiry>,
 ) -> Result<(), Error> {
     let world = worlds::get_data()
         .get_world_by_id(world)
         .cloned()
         .ok_or(Error::UnknownWorld)?;
-    subscribe_world(ctx, world, false).await
+    subscribe_world(ctx, world, create_options(repeat, expires), false).await
 }
 
-pub async fn subscribe_world(ctx: Context<'_>, world: World, ephemeral: bool) -> Result<(), Error> {
+pub async fn subscribe_world(
+    ctx: Context<'_>,
+    world: World,
+    options: SubscriptionOptions,
+    ephemeral: bool,
+) -> Result<(), Error> {
     let client = ctx.data();
     let db = client.db();
     let config = client.config();
@@ -120,6 +253,7 @@ pub async fn subscribe_world(ctx: Context<'_>, world: World, ephemeral: bool) ->
         create_travel_embed(
             &world.to_string(),
             vec![(&world, is_prohibited)],
+            &HashMap::new(),
             &config.emotes,
         )
         .description("This world is aleady open for travel.")
@@ -129,13 +263,17 @@ pub async fn subscribe_world(ctx: Context<'_>, world: World, ephemeral: bool) ->
             .subscribe(
                 Endpoint::World(world.id),
                 Subscriber::Discord(ctx.author().id.get()),
+                options,
             )
             .await?;
 
         if success {
             CreateEmbed::new()
                 .title(format!("Subscribed to {}", world))
-                .description("You will be reminded when this world is open for travel.")
+                .description(format!(
+                    "You will be reminded when this world is open for travel.{}",
+                    describe_options(&options)
+                ))
                 .color(COLOR_SUCCESS)
         } else {
             CreateEmbed::new()
@@ -154,3 +292,191 @@ pub async fn subscribe_world(ctx: Context<'_>, world: World, ephemeral: bool) ->
 
     Ok(())
 }
+
+/// List your DC travel reminders
+#[poise::command(slash_command)]
+async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    let client = ctx.data();
+    let subscriptions = client.subscriptions();
+    let subscriber = Subscriber::Discord(ctx.author().id.get());
+
+    let mut reminders = subscriptions.get_subscriptions(&subscriber).await?;
+    let (embed, components) = create_reminder_list(&reminders);
+    let reply = ctx
+        .send(
+            CreateReply::default()
+                .reply(true)
+                .embed(embed)
+                .components(components)
+                .ephemeral(true),
+        )
+        .await?;
+
+    while !reminders.is_empty() {
+        let Some(interaction) = reply
+            .message()
+            .await?
+            .await_component_interaction(ctx)
+            .author_id(ctx.author().id)
+            .timeout(std::time::Duration::from_secs(120))
+            .await
+        else {
+            break;
+        };
+
+        if let Some(endpoint) = parse_remove_reminder_id(&interaction.data.custom_id) {
+            subscriptions.unsubscribe(endpoint, &subscriber).await?;
+        }
+
+        reminders = subscriptions.get_subscriptions(&subscriber).await?;
+        let (embed, components) = create_reminder_list(&reminders);
+        interaction
+            .create_response(
+                ctx,
+                CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::new()
+                        .embed(embed)
+                        .components(components),
+                ),
+            )
+            .await?;
+    }
+
+    let (embed, _) = create_reminder_list(&reminders);
+    reply
+        .edit(ctx, CreateReply::default().embed(embed).components(vec![]))
+        .await?;
+
+    Ok(())
+}
+
+fn get_endpoint_name(endpoint: Endpoint) -> String {
+    let travel_data = worlds::get_data();
+    match endpoint {
+        Endpoint::Datacenter(id) => travel_data
+            .get_datacenter_by_id(id)
+            .map_or_else(|| format!("Unknown datacenter ({id})"), ToString::to_string),
+        Endpoint::World(id) => travel_data
+            .get_world_by_id(id)
+            .map_or_else(|| format!("Unknown world ({id})"), ToString::to_string),
+    }
+}
+
+fn create_remove_reminder_id(endpoint: Endpoint) -> String {
+    match endpoint {
+        Endpoint::Datacenter(id) => format!("remove_reminder:dc:{id}"),
+        Endpoint::World(id) => format!("remove_reminder:world:{id}"),
+    }
+}
+
+fn parse_remove_reminder_id(custom_id: &str) -> Option<Endpoint> {
+    let (endpoint_type, id) = custom_id
+        .strip_prefix("remove_reminder:")?
+        .split_once(':')?;
+    let id = id.parse().ok()?;
+    match endpoint_type {
+        "dc" => Some(Endpoint::Datacenter(id)),
+        "world" => Some(Endpoint::World(id)),
+        _ => None,
+    }
+}
+
+// Discord allows at most 5 rows of 5 buttons each
+const MAX_REMOVE_BUTTONS: usize = 25;
+
+fn create_reminder_list(reminders: &[SubscriptionInfo]) -> (CreateEmbed, Vec<CreateActionRow>) {
+    if reminders.is_empty() {
+        let embed = CreateEmbed::new()
+            .title("No reminders")
+            .description("You don't have any reminders. Use /remind to add one.")
+            .color(COLOR_ERROR);
+        return (embed, vec![]);
+    }
+
+    let description = reminders
+        .iter()
+        .map(|reminder| {
+            let kind = match reminder.endpoint {
+                Endpoint::Datacenter(_) => "Datacenter",
+                Endpoint::World(_) => "World",
+            };
+            let mut line = format!(
+                "**{}** ({}) - added {}",
+                get_endpoint_name(reminder.endpoint),
+                kind,
+                FormattedTimestamp::new(
+                    reminder.created_at.0.into(),
+                    Some(FormattedTimestampStyle::RelativeTime)
+                )
+            );
+            match reminder.options.repeat_cooldown {
+                None => {}
+                Some(cooldown) if cooldown.is_zero() => line.push_str(" - repeats"),
+                Some(cooldown) => line.push_str(&format!(
+                    " - repeats at most every {}",
+                    format_cooldown(cooldown)
+                )),
+            }
+            if let Some(expires_at) = reminder.options.expires_at {
+                line.push_str(&format!(
+                    " - expires {}",
+                    FormattedTimestamp::new(
+                        expires_at.0.into(),
+                        Some(FormattedTimestampStyle::RelativeTime)
+                    )
+                ));
+            }
+            if reminder.is_pending {
+                line.push_str(" - *open now, reminder on its way*");
+            }
+            line
+        })
+        .join("\n");
+
+    let mut embed = CreateEmbed::new()
+        .title("Your Reminders")
+        .description(description)
+        .color(COLOR_SUCCESS);
+    if reminders.len() > MAX_REMOVE_BUTTONS {
+        embed = embed.footer(CreateEmbedFooter::new(
+            "Only the oldest 25 reminders can be removed here. Use /remindoff for the rest.",
+        ));
+    }
+
+    let components = reminders
+        .iter()
+        .take(MAX_REMOVE_BUTTONS)
+        .map(|reminder| {
+            CreateButton::new(create_remove_reminder_id(reminder.endpoint))
+                .label(format!("Remove {}", get_endpoint_name(reminder.endpoint)))
+                .style(ButtonStyle::Danger)
+        })
+        .chunks(5)
+        .into_iter()
+        .map(|buttons| CreateActionRow::Buttons(buttons.collect()))
+        .collect();
+
+    (embed, components)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remove_reminder_id_roundtrip() {
+        for endpoint in [Endpoint::Datacenter(4), Endpoint::World(1234)] {
+            let custom_id = create_remove_reminder_id(endpoint);
+            assert_eq!(parse_remove_reminder_id(&custom_id), Some(endpoint));
+        }
+    }
+
+    #[test]
+    fn test_parse_remove_reminder_id_invalid() {
+        assert_eq!(parse_remove_reminder_id("remove_reminder:dc"), None);
+        assert_eq!(parse_remove_reminder_id("remove_reminder:dc:abc"), None);
+        assert_eq!(parse_remove_reminder_id("remove_reminder:region:1"), None);
+        assert_eq!(parse_remove_reminder_id("other:dc:1"), None);
+        assert_eq!(parse_remove_reminder_id("remove_reminder:world:70000"), None);
+    }
+}
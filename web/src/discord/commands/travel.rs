@@ -4,12 +4,17 @@ use super::{
     subscribe::{subscribe_datacenter, subscribe_world},
     utils::{autocomplete_world, create_travel_embed},
 };
-use crate::storage::{
-    db,
-    game::worlds::{self, Datacenter},
+use crate::{
+    estimators,
+    storage::{
+        db,
+        game::worlds::{self, Datacenter},
+    },
+    subscriptions::SubscriptionOptions,
 };
 use ::serenity::all::{EditMessage, ReactionType};
 use poise::{serenity_prelude as serenity, CreateReply};
+use std::collections::HashMap;
 
 #[poise::command(
     slash_command,
@@ -48,7 +53,19 @@ async fn datacenter(
         })
         .collect::<Result<Vec<_>, _>>()?;
 
-    let embed = create_travel_embed(&datacenter.to_string(), worlds, &config.emotes);
+    let closed_world_ids = worlds
+        .iter()
+        .filter(|(_, status)| *status)
+        .map(|(world, _)| world.id)
+        .collect();
+    let predictions = estimators::travel::predict_openings(db, closed_world_ids).await?;
+
+    let embed = create_travel_embed(
+        &datacenter.to_string(),
+        worlds,
+        &predictions,
+        &config.emotes,
+    );
 
     let components = if is_all_prohibited {
         vec![serenity::CreateActionRow::Buttons(vec![
@@ -80,7 +97,7 @@ async fn datacenter(
             .await
         {
             if interaction.data.custom_id == "set_reminder" {
-                subscribe_datacenter(ctx, datacenter, true).await?;
+                subscribe_datacenter(ctx, datacenter, SubscriptionOptions::default(), true).await?;
             }
             interaction
                 .create_response(ctx, serenity::CreateInteractionResponse::Acknowledge)
@@ -122,9 +139,16 @@ async fn world(
         .copied()
         .unwrap_or_default();
 
+    let predictions = if is_prohibited {
+        estimators::travel::predict_openings(db, vec![world.id]).await?
+    } else {
+        HashMap::new()
+    };
+
     let embed = create_travel_embed(
         &world.to_string(),
         vec![(&world, is_prohibited)],
+        &predictions,
         &config.emotes,
     );
 
@@ -158,7 +182,7 @@ async fn world(
             .await
         {
             if interaction.data.custom_id == "set_reminder" {
-                subscribe_world(ctx, world, true).await?;
+                subscribe_world(ctx, world, SubscriptionOptions::default(), true).await?;
             }
             interaction
                 .create_response(ctx, serenity::CreateInteractionResponse::Acknowledge)
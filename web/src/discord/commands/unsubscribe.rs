@@ -14,7 +14,7 @@ use poise::CreateReply;
     install_context = "Guild|User",
     interaction_context = "Guild|BotDm|PrivateChannel",
     rename = "remindoff",
-    subcommands("datacenter", "world")
+    subcommands("datacenter", "world", "all")
 )]
 #[allow(clippy::unused_async)]
 pub async fn unsubscribe(_: Context<'_>) -> Result<(), Error> {
@@ -92,3 +92,34 @@ async fn world(
 
     Ok(())
 }
+
+/// Remove all of your DC travel reminders
+#[poise::command(slash_command)]
+async fn all(ctx: Context<'_>) -> Result<(), Error> {
+    let client = ctx.data();
+    let subscriptions = client.subscriptions();
+
+    let count = subscriptions
+        .unsubscribe_all(&Subscriber::Discord(ctx.author().id.get()))
+        .await?;
+    let embed = if count != 0 {
+        CreateEmbed::new()
+            .title("Unsubscribed from all reminders")
+            .description(format!(
+                "Removed {} reminder{}. You will no longer be reminded when DC travel opens.",
+                count,
+                if count == 1 { "" } else { "s" }
+            ))
+            .color(COLOR_SUCCESS)
+    } else {
+        CreateEmbed::new()
+            .title("No reminders")
+            .description("You don't have any reminders.")
+            .color(COLOR_ERROR)
+    };
+
+    ctx.send(CreateReply::default().reply(true).embed(embed))
+        .await?;
+
+    Ok(())
+}
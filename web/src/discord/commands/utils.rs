@@ -1,16 +1,27 @@
 use crate::{
     config::DiscordEmoteConfig,
     discord::utils::{
-        format_queue_duration, COLOR_DC_ALLOWED, COLOR_DC_MIXED, COLOR_DC_PROHIBITED,
+        format_duration_duty_eta, format_queue_duration, COLOR_DC_ALLOWED, COLOR_DC_MIXED,
+        COLOR_DC_PROHIBITED, COLOR_IN_QUEUE,
+    },
+    middleware::version::ClientVersion,
+    models::{
+        client_version::ClientVersionStat,
+        duty::{RoleDemand, RouletteEstimate, RoulettePosition, RouletteRole, WaitTime},
+        login::{DatacenterErrorRate, QueueEstimate},
+        travel::TravelPrediction,
+    },
+    storage::game::{
+        content, get_icon_url,
+        worlds::{self, Datacenter, World},
     },
-    models::login::QueueEstimate,
-    storage::game::worlds::{self, World},
 };
 use ::serenity::all::{
     Color, CreateEmbed, CreateEmbedFooter, FormattedTimestamp, FormattedTimestampStyle,
 };
 use itertools::Itertools;
 use poise::serenity_prelude as serenity;
+use std::collections::HashMap;
 use time::OffsetDateTime;
 
 use super::Context;
@@ -28,6 +39,7 @@ pub async fn autocomplete_world<'a>(
 pub fn create_travel_embed(
     name: &str,
     worlds: Vec<(&World, bool)>,
+    predictions: &HashMap<u16, TravelPrediction>,
     config: &DiscordEmoteConfig,
 ) -> CreateEmbed {
     let color = match worlds.iter().filter(|(_, s)| *s).count() {
@@ -39,8 +51,12 @@ pub fn create_travel_embed(
     let embed = CreateEmbed::new().title(format!("DC Travel for {name}"));
 
     let embed = if worlds.len() == 1 {
-        let is_prohibited = worlds.first().expect("worlds is not empty").1;
-        embed.description(format_travel_status(is_prohibited, config))
+        let (world, is_prohibited) = worlds.first().expect("worlds is not empty");
+        embed.description(format_travel_status(
+            *is_prohibited,
+            predictions.get(&world.id),
+            config,
+        ))
     } else {
         embed.fields(
             worlds
@@ -49,7 +65,7 @@ pub fn create_travel_embed(
                 .map(|(world, is_prohibited)| {
                     (
                         world.name.clone(),
-                        format_travel_status(is_prohibited, config),
+                        format_travel_status(is_prohibited, predictions.get(&world.id), config),
                         true,
                     )
                 }),
@@ -62,8 +78,12 @@ pub fn create_travel_embed(
         .color(color)
 }
 
-fn format_travel_status(is_prohibited: bool, config: &DiscordEmoteConfig) -> String {
-    format!(
+fn format_travel_status(
+    is_prohibited: bool,
+    prediction: Option<&TravelPrediction>,
+    config: &DiscordEmoteConfig,
+) -> String {
+    let status = format!(
         "{} {}",
         if !is_prohibited {
             &config.green_check
@@ -75,7 +95,22 @@ fn format_travel_status(is_prohibited: bool, config: &DiscordEmoteConfig) -> Str
         } else {
             "Prohibited"
         }
-    )
+    );
+    match prediction {
+        Some(prediction) if is_prohibited => {
+            format!("{status}\n{}", format_travel_prediction(prediction))
+        }
+        _ => status,
+    }
+}
+
+fn format_travel_prediction(prediction: &TravelPrediction) -> String {
+    let chances = prediction
+        .open_probabilities
+        .iter()
+        .map(|p| format!("{}m: {:.0}%", p.minutes, p.probability * 100.0))
+        .join(" | ");
+    format!("Chance to open within {chances}")
 }
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -100,7 +135,7 @@ impl From<QueueColor> for Color {
 
 impl QueueColor {
     fn from_estimate(estimate: &QueueEstimate) -> Self {
-        let a = Self::from_duration(time::Duration::seconds_f64(estimate.last_duration));
+        let a = Self::from_duration(time::Duration::seconds_f64(estimate.typical_duration()));
         let b = Self::from_size(estimate.last_size);
         std::cmp::max(a, b)
     }
@@ -157,14 +192,23 @@ pub fn create_queue_embed(name: &str, worlds: Vec<(&World, QueueEstimate)>) -> C
 }
 
 fn format_queue_time(estimate: &QueueEstimate, add_updated: bool) -> String {
-    let mut result = if estimate.last_duration == 0f64 && estimate.last_size == 0 {
+    let mut result = if estimate.typical_duration() == 0f64 && estimate.last_size == 0 {
         "Instant".to_string()
-    } else {
+    } else if estimate.sample_count == 0 {
         format!(
-            "Size: {}\nTime: {}",
+            "Size: {}\nTime: {}\nNo recent queues",
             estimate.last_size,
             format_queue_duration(time::Duration::seconds_f64(estimate.last_duration))
         )
+    } else {
+        format!(
+            "Size: {}\nTime: {} (up to {})\n{} confidence ({} queues)",
+            estimate.last_size,
+            format_queue_duration(time::Duration::seconds_f64(estimate.p50_duration)),
+            format_queue_duration(time::Duration::seconds_f64(estimate.p90_duration)),
+            estimate.confidence,
+            estimate.sample_count
+        )
     };
     if add_updated {
         result.push_str(&format!(
@@ -177,3 +221,211 @@ fn format_queue_time(estimate: &QueueEstimate, add_updated: bool) -> String {
     }
     result
 }
+
+pub fn create_error_rate_embed(
+    name: &str,
+    datacenters: Vec<(&Datacenter, DatacenterErrorRate)>,
+) -> CreateEmbed {
+    let embed = CreateEmbed::new().title(format!("Login Errors for {name}"));
+
+    let embed = if datacenters.is_empty() {
+        embed.description("No queues in the last hour.")
+    } else {
+        embed
+            .description("Login queues that ended in an error over the last hour.")
+            .fields(
+                datacenters
+                    .into_iter()
+                    .sorted_unstable_by_key(|(datacenter, _)| datacenter.id)
+                    .map(|(datacenter, rate)| {
+                        (datacenter.name.clone(), format_error_rate(&rate), true)
+                    }),
+            )
+    };
+
+    embed
+        .footer(CreateEmbedFooter::new("Last updated"))
+        .timestamp(OffsetDateTime::now_utc())
+        .color(Color::RED)
+}
+
+fn format_error_rate(rate: &DatacenterErrorRate) -> String {
+    if rate.total == 0 {
+        return "No queues".to_string();
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    let percent = rate.errors as f64 / rate.total as f64 * 100.0;
+    let mut result = format!("{} / {} ({percent:.1}%)", rate.errors, rate.total);
+    for (code, count) in rate.codes.iter().take(3) {
+        result.push_str(&format!("\nError {code}: {count}"));
+    }
+    result
+}
+
+pub fn create_client_version_embed(stats: &[ClientVersionStat]) -> CreateEmbed {
+    let embed = CreateEmbed::new().title("Client Versions");
+
+    let Some(latest_day) = stats.iter().map(|s| s.day).max() else {
+        return embed.description("No recaps in the last week.");
+    };
+
+    let fields = stats
+        .iter()
+        .into_group_map_by(|s| (s.version.clone(), s.configuration.clone()))
+        .into_iter()
+        .sorted_unstable_by_key(|((version, configuration), _)| {
+            (
+                std::cmp::Reverse(ClientVersion::try_from(version.clone()).ok()),
+                configuration.clone(),
+            )
+        })
+        .take(25)
+        .map(|((version, configuration), stats)| {
+            let recaps: i64 = stats.iter().map(|s| s.recap_count).sum();
+            let latest = stats.iter().filter(|s| s.day == latest_day).collect_vec();
+            let login_users: i64 = latest
+                .iter()
+                .filter(|s| s.source == "login")
+                .map(|s| s.user_count)
+                .sum();
+            let duty_users: i64 = latest
+                .iter()
+                .filter(|s| s.source == "duty")
+                .map(|s| s.user_count)
+                .sum();
+            (
+                format!("{version} ({configuration})"),
+                format!("Login users: {login_users}\nDuty users: {duty_users}\nRecaps: {recaps}"),
+                true,
+            )
+        });
+
+    embed
+        .description(format!(
+            "Users are counted on {}. Recaps are counted over the last week.",
+            FormattedTimestamp::new(latest_day.0.into(), Some(FormattedTimestampStyle::LongDate))
+        ))
+        .fields(fields)
+        .footer(CreateEmbedFooter::new("Last updated"))
+        .timestamp(OffsetDateTime::now_utc())
+}
+
+pub fn create_role_demand_embed(
+    name: &str,
+    demand: Vec<RoleDemand>,
+    config: &DiscordEmoteConfig,
+) -> CreateEmbed {
+    let embed = CreateEmbed::new().title(format!("Role Demand for {name}"));
+
+    let embed = if demand.is_empty() {
+        embed.description("No roulette data in the last day.")
+    } else {
+        embed
+            .description(
+                "Roulette waits compared to the average of all roles over the last day. \
+                Shorter waits mean the role is in demand.",
+            )
+            .fields(
+                demand
+                    .into_iter()
+                    .sorted_unstable_by(|a, b| {
+                        a.relative_wait
+                            .unwrap_or(f64::INFINITY)
+                            .total_cmp(&b.relative_wait.unwrap_or(f64::INFINITY))
+                    })
+                    .map(|demand| {
+                        let (emote, role) = match demand.role {
+                            RouletteRole::Tank => (&config.duty_tank, "Tank"),
+                            RouletteRole::Healer => (&config.duty_healer, "Healer"),
+                            RouletteRole::Dps => (&config.duty_dps, "DPS"),
+                        };
+                        (format!("{emote} {role}"), format_role_demand(&demand), true)
+                    }),
+            )
+    };
+
+    embed
+        .footer(CreateEmbedFooter::new("Last updated"))
+        .timestamp(OffsetDateTime::now_utc())
+        .color(COLOR_IN_QUEUE)
+}
+
+fn format_role_demand(demand: &RoleDemand) -> String {
+    let wait = demand.relative_wait.map_or_else(
+        || "Not enough data".to_string(),
+        |wait| format!("{:+.0}% wait", (wait - 1.0) * 100.0),
+    );
+    let fill = demand.fill_rate.map_or_else(
+        || "Not enough data".to_string(),
+        |fill| format!("{:.0}% filled", fill * 100.0),
+    );
+    format!(
+        "{wait} ({} roulettes)\n{fill} ({} updates)",
+        demand.wait_sample_count, demand.fill_sample_count
+    )
+}
+
+pub fn create_roulette_embed(
+    name: &str,
+    estimates: Vec<RouletteEstimate>,
+    config: &DiscordEmoteConfig,
+) -> CreateEmbed {
+    let embed = CreateEmbed::new().title(format!("Roulettes for {name}"));
+    let content_data = content::get_data();
+
+    let roulettes = estimates
+        .into_iter()
+        .into_group_map_by(|e| e.roulette_id)
+        .into_iter()
+        .sorted_unstable_by_key(|(id, _)| *id)
+        .collect_vec();
+
+    let embed = match roulettes.as_slice() {
+        [] => embed.description("No roulette data yet."),
+        [(id, _)] => embed.thumbnail(get_icon_url(&content_data.get_roulette_image(*id))),
+        _ => embed,
+    };
+
+    embed
+        .fields(roulettes.into_iter().take(25).map(|(id, estimates)| {
+            let value = estimates
+                .into_iter()
+                .sorted_unstable_by_key(|e| e.role as u8)
+                .map(|e| format_roulette_estimate(&e, config))
+                .join("\n");
+            (content_data.get_roulette_name(id), value, false)
+        }))
+        .footer(CreateEmbedFooter::new("Last updated"))
+        .timestamp(OffsetDateTime::now_utc())
+        .color(COLOR_IN_QUEUE)
+}
+
+fn format_roulette_estimate(estimate: &RouletteEstimate, config: &DiscordEmoteConfig) -> String {
+    let emote = match estimate.role {
+        RouletteRole::Tank => &config.duty_tank,
+        RouletteRole::Healer => &config.duty_healer,
+        RouletteRole::Dps => &config.duty_dps,
+    };
+    let eta = match estimate.estimated_wait_time {
+        WaitTime::Minutes(mins) => format_duration_duty_eta(time::Duration::minutes(mins.into())),
+        WaitTime::Over30Minutes => "30m+".to_string(),
+        WaitTime::Hidden => "Unknown".to_string(),
+    };
+    let position = match estimate.size {
+        RoulettePosition::Position(p) => format!("#{p}"),
+        RoulettePosition::After50 => "#50+".to_string(),
+        RoulettePosition::RetrievingInfo => "Unknown".to_string(),
+    };
+    format!(
+        "{emote} **{}** (ETA {eta}, Position {position}) {}",
+        estimate.wait_time.map_or_else(
+            || "Unknown".to_string(),
+            |w| format_queue_duration(time::Duration::seconds_f64(w))
+        ),
+        FormattedTimestamp::new(
+            estimate.last_update.0.into(),
+            Some(FormattedTimestampStyle::RelativeTime)
+        )
+    )
+}
@@ -178,6 +178,7 @@ pub async fn send_delete(
     resulting_content: Option<u16>,
     error_message: Option<String>,
     error_code: Option<u16>,
+    withdraw_message: Option<u16>,
 ) -> Result<(), serenity::Error> {
     channel_id
         .edit_message(
@@ -189,10 +190,12 @@ pub async fn send_delete(
                 position_end,
                 duration,
                 resulting_content,
-                match (error_message, error_code) {
-                    (Some(message), Some(code)) => Some((message, code)),
+                match (&error_message, error_code) {
+                    (Some(message), Some(code)) => Some((message.clone(), code)),
                     _ => None,
                 },
+                // Without a message, the error code is the withdraw reason's log message
+                withdraw_message.or(error_code.filter(|_| error_message.is_none())),
             )),
         )
         .await?;
@@ -258,6 +261,7 @@ fn create_completion_embed(
     duration: Duration,
     resulting_content: Option<u16>,
     error: Option<(String, u16)>,
+    withdraw_message: Option<u16>,
 ) -> CreateEmbed {
     match resulting_content {
         Some(content) => {
@@ -272,6 +276,7 @@ fn create_completion_embed(
             },
             duration,
             error,
+            withdraw_message,
         ),
     }
 }
@@ -333,9 +338,12 @@ fn create_completion_embed_unsuccessful(
     position: Option<Either<RoulettePosition, (RoulettePosition, RoulettePosition)>>,
     duration: Duration,
     error: Option<(String, u16)>,
+    withdraw_message: Option<u16>,
 ) -> CreateEmbed {
     let mut msg = if let Some((message, _code)) = error {
         message
+    } else if let Some(withdraw_message) = withdraw_message {
+        content::get_data().get_withdraw_reason(withdraw_message)
     } else {
         "You left the queue!".to_string()
     };
@@ -1,6 +1,11 @@
-use crate::discord::{
-    utils::{format_duration, format_queue_duration, COLOR_ERROR, COLOR_IN_QUEUE, COLOR_SUCCESS},
-    DiscordClient,
+use crate::{
+    discord::{
+        utils::{
+            format_duration, format_queue_duration, COLOR_ERROR, COLOR_IN_QUEUE, COLOR_SUCCESS,
+        },
+        DiscordClient,
+    },
+    estimators,
 };
 use actix_web::Result;
 use serenity::all::{
@@ -13,11 +18,13 @@ pub async fn send_queue_position(
     discord: &DiscordClient,
     user_id: UserId,
     character_name: &str,
+    world_id: u16,
     position: u32,
     now: time::OffsetDateTime,
     estimated: time::OffsetDateTime,
 ) -> Result<Message, serenity::Error> {
     let channel = user_id.create_dm_channel(discord.http()).await?;
+    let server_estimated = estimate_login_time(discord, world_id, position, now).await;
 
     channel
         .send_message(
@@ -27,30 +34,56 @@ pub async fn send_queue_position(
                 position,
                 now,
                 estimated,
+                server_estimated,
             )),
         )
         .await
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn update_queue_position(
     discord: &DiscordClient,
     message_id: MessageId,
     channel_id: ChannelId,
     character_name: &str,
+    world_id: u16,
     position: u32,
     now: time::OffsetDateTime,
     estimated: time::OffsetDateTime,
 ) -> Result<(), serenity::Error> {
+    let server_estimated = estimate_login_time(discord, world_id, position, now).await;
+
     channel_id
         .edit_message(
             discord.http(),
             message_id,
-            EditMessage::new().embed(create_queue_embed(character_name, position, now, estimated)),
+            EditMessage::new().embed(create_queue_embed(
+                character_name,
+                position,
+                now,
+                estimated,
+                server_estimated,
+            )),
         )
         .await?;
     Ok(())
 }
 
+async fn estimate_login_time(
+    discord: &DiscordClient,
+    world_id: u16,
+    position: u32,
+    now: time::OffsetDateTime,
+) -> Option<time::OffsetDateTime> {
+    match estimators::login::estimate_wait(discord.db(), world_id, position).await {
+        Ok(estimate) => estimate.map(|e| now + Duration::seconds_f64(e.duration)),
+        Err(e) => {
+            log::error!("Failed to estimate wait for world {world_id}: {e:?}");
+            None
+        }
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 pub async fn send_queue_completion(
     discord: &DiscordClient,
@@ -188,16 +221,29 @@ fn create_queue_embed(
     position: u32,
     now: time::OffsetDateTime,
     estimated: time::OffsetDateTime,
+    server_estimated: Option<time::OffsetDateTime>,
 ) -> CreateEmbed {
     let estimated: Timestamp = estimated.into();
+    let description = if let Some(server_estimated) = server_estimated {
+        let server_estimated: Timestamp = server_estimated.into();
+        format!(
+            "You're in position {}. You'll login {} ({})\nYour client estimates {}.\n\nYou'll receive a DM from me when your queue completes.",
+            position,
+            FormattedTimestamp::new(server_estimated, Some(FormattedTimestampStyle::RelativeTime)),
+            FormattedTimestamp::new(server_estimated, Some(FormattedTimestampStyle::LongTime)),
+            FormattedTimestamp::new(estimated, Some(FormattedTimestampStyle::RelativeTime)),
+        )
+    } else {
+        format!(
+            "You're in position {}. You'll login {} ({})\n\nYou'll receive a DM from me when your queue completes.",
+            position,
+            FormattedTimestamp::new(estimated, Some(FormattedTimestampStyle::RelativeTime)),
+            FormattedTimestamp::new(estimated, Some(FormattedTimestampStyle::LongTime)),
+        )
+    };
     CreateEmbed::new()
             .title("Login Queue")
-            .description(format!(
-                "You're in position {}. You'll login {} ({})\n\nYou'll receive a DM from me when your queue completes.",
-                position,
-                FormattedTimestamp::new(estimated, Some(FormattedTimestampStyle::RelativeTime)),
-                FormattedTimestamp::new(estimated, Some(FormattedTimestampStyle::LongTime)),
-            ))
+            .description(description)
             .author(CreateEmbedAuthor::new(character_name))
             .footer(CreateEmbedFooter::new("Last updated"))
             .timestamp(now)
@@ -0,0 +1,27 @@
+use crate::{models::login::QueueWaitEstimate, storage::db};
+use sqlx::{Error, PgPool};
+
+// Minimum number of recent recaps required before the throughput is trusted
+const MIN_SAMPLE_COUNT: i64 = 3;
+
+pub async fn estimate_wait(
+    pool: &PgPool,
+    world_id: u16,
+    position: u32,
+) -> Result<Option<QueueWaitEstimate>, Error> {
+    let Some(throughput) = db::login::get_queue_throughput(pool, world_id).await? else {
+        return Ok(None);
+    };
+
+    if throughput.sample_count < MIN_SAMPLE_COUNT || throughput.throughput <= 0.0 {
+        return Ok(None);
+    }
+
+    Ok(Some(QueueWaitEstimate {
+        world_id,
+        position,
+        duration: f64::from(position) / throughput.throughput * 60.0,
+        throughput: throughput.throughput,
+        sample_count: throughput.sample_count,
+    }))
+}
@@ -0,0 +1,156 @@
+use crate::{
+    models::duty::{QueueLanguage, RouletteEstimate},
+    storage::db,
+};
+use itertools::Itertools;
+use sqlx::{Error, PgPool};
+
+// An estimate this old counts for half as much as one reported just now
+const HALF_LIFE: time::Duration = time::Duration::hours(1);
+
+// Merges the estimates of every language combination that is a subset or
+// superset of the requested one, since those queues draw from mostly the
+// same pool of players
+pub async fn estimate_compatible(
+    pool: &PgPool,
+    datacenter_id: u16,
+    languages: QueueLanguage,
+    roulette_ids: Option<Vec<u8>>,
+) -> Result<Vec<RouletteEstimate>, Error> {
+    let estimates = db::duty::get_roulette_estimates_by_datacenter_id_compatible(
+        pool,
+        datacenter_id,
+        languages,
+        roulette_ids,
+    )
+    .await?;
+
+    Ok(merge_estimates(
+        estimates,
+        languages,
+        time::OffsetDateTime::now_utc(),
+    ))
+}
+
+fn merge_estimates(
+    estimates: Vec<RouletteEstimate>,
+    languages: QueueLanguage,
+    now: time::OffsetDateTime,
+) -> Vec<RouletteEstimate> {
+    estimates
+        .into_iter()
+        .into_group_map_by(|e| (e.roulette_id, e.role as u8))
+        .into_values()
+        .filter_map(|estimates| {
+            // Only estimates with a reported wait time take part, weighted by when it was reported
+            let (weighted_sum, total_weight) = estimates
+                .iter()
+                .filter_map(|e| Some((e.wait_time?, e.wait_time_update?)))
+                .fold((0.0, 0.0), |(sum, total), (wait_time, update)| {
+                    let age = (now - update.0).max(time::Duration::ZERO);
+                    let weight = 0.5f64.powf(age / HALF_LIFE);
+                    (sum + wait_time * weight, total + weight)
+                });
+            // Falls back to the newest wait time if every sample is old enough to weigh nothing
+            let newest = estimates
+                .iter()
+                .filter(|e| e.wait_time.is_some())
+                .max_by_key(|e| e.wait_time_update);
+            let wait_time_update = newest.and_then(|e| e.wait_time_update);
+            let wait_time = Some(weighted_sum / total_weight)
+                .filter(|w| w.is_finite())
+                .or_else(|| newest.and_then(|e| e.wait_time));
+
+            // The position and in-game estimate can't be averaged, so use the latest
+            let latest = estimates.into_iter().max_by_key(|e| e.last_update)?;
+            Some(RouletteEstimate {
+                languages,
+                wait_time,
+                wait_time_update,
+                ..latest
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::duty::{RoulettePosition, RouletteRole, WaitTime};
+
+    const NOW: time::OffsetDateTime = time::OffsetDateTime::UNIX_EPOCH;
+
+    fn estimate(
+        languages: QueueLanguage,
+        last_update: time::OffsetDateTime,
+        wait_time: Option<(f64, time::OffsetDateTime)>,
+    ) -> RouletteEstimate {
+        RouletteEstimate {
+            datacenter_id: 1,
+            languages,
+            roulette_id: 1,
+            role: RouletteRole::Dps,
+            last_update: last_update.into(),
+            wait_time: wait_time.map(|(w, _)| w),
+            wait_time_update: wait_time.map(|(_, t)| t.into()),
+            size: RoulettePosition::Position(1),
+            estimated_wait_time: WaitTime::Minutes(5),
+        }
+    }
+
+    #[test]
+    fn test_merge_estimates_weights_by_wait_time_age() {
+        let merged = merge_estimates(
+            vec![
+                estimate(QueueLanguage::Jp, NOW, Some((100.0, NOW))),
+                // Recently updated, but the wait time itself is an hour old
+                estimate(QueueLanguage::En, NOW, Some((400.0, NOW - HALF_LIFE))),
+            ],
+            QueueLanguage::JpEn,
+            NOW,
+        );
+
+        assert_eq!(merged.len(), 1);
+        assert_eq!(u8::from(merged[0].languages), u8::from(QueueLanguage::JpEn));
+        // (100 * 1 + 400 * 0.5) / 1.5
+        assert!((merged[0].wait_time.unwrap() - 200.0).abs() < 1e-9);
+        assert_eq!(merged[0].wait_time_update, Some(NOW.into()));
+    }
+
+    #[test]
+    fn test_merge_estimates_skips_missing_wait_times() {
+        let merged = merge_estimates(
+            vec![
+                estimate(QueueLanguage::Jp, NOW, None),
+                estimate(
+                    QueueLanguage::En,
+                    NOW - HALF_LIFE,
+                    Some((300.0, NOW - HALF_LIFE)),
+                ),
+            ],
+            QueueLanguage::JpEn,
+            NOW,
+        );
+
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].wait_time, Some(300.0));
+        // Everything else still comes from the latest estimate
+        assert_eq!(merged[0].last_update, NOW.into());
+    }
+
+    #[test]
+    fn test_merge_estimates_without_wait_times() {
+        let merged = merge_estimates(
+            vec![
+                estimate(QueueLanguage::Jp, NOW, None),
+                estimate(QueueLanguage::En, NOW, None),
+            ],
+            QueueLanguage::JpEn,
+            NOW,
+        );
+
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].wait_time, None);
+        assert_eq!(merged[0].wait_time_update, None);
+    }
+}
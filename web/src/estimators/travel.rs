@@ -0,0 +1,189 @@
+use crate::{
+    models::travel::{DbTravelClosure, OpenProbability, TravelPrediction},
+    storage::db::{self, wrappers::DatabaseU16},
+};
+use itertools::Itertools;
+use sqlx::{Error, PgPool};
+use std::collections::HashMap;
+
+// How far back to look for past closures
+const HISTORY: time::Duration = time::Duration::days(28);
+// Closures that started this many hours around the current one's start hour
+// are preferred, since congestion follows the daily playtime curve
+const HOUR_WINDOW: u8 = 2;
+// Fall back to closures from any hour if there are fewer than this many
+const MIN_HOURLY_SAMPLES: usize = 10;
+// Don't predict anything from fewer than this many closures
+const MIN_SAMPLES: usize = 3;
+
+const HORIZONS: [u32; 3] = [15, 30, 60];
+
+// Predicts when each closed world will open, based on how long its past
+// closures lasted given that they had already lasted as long as this one
+pub async fn predict_openings(
+    pool: &PgPool,
+    world_ids: Vec<u16>,
+) -> Result<HashMap<u16, TravelPrediction>, Error> {
+    let now = time::OffsetDateTime::now_utc();
+    let closures = db::travel::get_travel_closures(pool, world_ids, (now - HISTORY).into()).await?;
+
+    Ok(closures
+        .into_iter()
+        .chunk_by(|c| c.world_id)
+        .into_iter()
+        .filter_map(|(world_id, closures)| {
+            let closures = closures.collect_vec();
+            predict_opening(&closures, now).map(|p| (DatabaseU16::from(world_id).0, p))
+        })
+        .collect())
+}
+
+fn predict_opening(
+    closures: &[DbTravelClosure],
+    now: time::OffsetDateTime,
+) -> Option<TravelPrediction> {
+    let (current, past) = closures.split_last()?;
+    if current.end.is_some() {
+        return None;
+    }
+
+    let elapsed = now - current.start.0;
+    let current_hour = current.start.0.hour();
+
+    // Durations of finished closures that lasted at least as long as the current one
+    let at_risk = past
+        .iter()
+        .filter(|c| !c.truncated)
+        .filter_map(|c| c.end.map(|end| (c.start.0.hour(), end.0 - c.start.0)))
+        .filter(|(_, duration)| *duration > elapsed)
+        .collect_vec();
+
+    let hourly = at_risk
+        .iter()
+        .filter(|(hour, _)| hour_distance(*hour, current_hour) <= HOUR_WINDOW)
+        .map(|(_, duration)| *duration)
+        .collect_vec();
+    let durations = if hourly.len() >= MIN_HOURLY_SAMPLES {
+        hourly
+    } else {
+        at_risk
+            .into_iter()
+            .map(|(_, duration)| duration)
+            .collect_vec()
+    };
+
+    if durations.len() < MIN_SAMPLES {
+        return None;
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    let open_probabilities = HORIZONS
+        .iter()
+        .map(|&minutes| {
+            let cutoff = elapsed + time::Duration::minutes(minutes.into());
+            let opened = durations.iter().filter(|d| **d <= cutoff).count();
+            OpenProbability {
+                minutes,
+                probability: opened as f64 / durations.len() as f64,
+            }
+        })
+        .collect();
+
+    Some(TravelPrediction {
+        closed_since: current.start,
+        open_probabilities,
+        sample_count: durations.len(),
+    })
+}
+
+fn hour_distance(a: u8, b: u8) -> u8 {
+    let d = a.abs_diff(b);
+    d.min(24 - d)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Noon, so closures around it don't wrap past midnight
+    fn now() -> time::OffsetDateTime {
+        time::OffsetDateTime::UNIX_EPOCH + time::Duration::hours(12)
+    }
+
+    fn closure(started_ago: i64, lasted: Option<i64>) -> DbTravelClosure {
+        let start = now() - time::Duration::minutes(started_ago);
+        DbTravelClosure {
+            world_id: 1,
+            start: start.into(),
+            end: lasted.map(|m| (start + time::Duration::minutes(m)).into()),
+            truncated: false,
+        }
+    }
+
+    fn probabilities(prediction: &TravelPrediction) -> Vec<f64> {
+        prediction
+            .open_probabilities
+            .iter()
+            .map(|p| p.probability)
+            .collect()
+    }
+
+    #[test]
+    fn test_predict_opening() {
+        // The current closure has lasted 10 minutes so far
+        let closures = [
+            closure(600, Some(5)),
+            closure(500, Some(20)),
+            closure(400, Some(30)),
+            closure(300, Some(60)),
+            closure(200, Some(120)),
+            closure(10, None),
+        ];
+        let prediction = predict_opening(&closures, now()).unwrap();
+
+        // The 5 minute closure is ruled out since this one has lasted longer
+        assert_eq!(prediction.sample_count, 4);
+        assert_eq!(prediction.closed_since, closures[5].start);
+        assert_eq!(probabilities(&prediction), vec![0.25, 0.5, 0.75]);
+    }
+
+    #[test]
+    fn test_predict_opening_for_open_world() {
+        let closures = [
+            closure(500, Some(20)),
+            closure(400, Some(30)),
+            closure(300, Some(60)),
+            closure(200, Some(5)),
+        ];
+        assert!(predict_opening(&closures, now()).is_none());
+    }
+
+    #[test]
+    fn test_predict_opening_needs_samples() {
+        let closures = [
+            closure(500, Some(20)),
+            closure(400, Some(30)),
+            closure(10, None),
+        ];
+        assert!(predict_opening(&closures, now()).is_none());
+    }
+
+    #[test]
+    fn test_predict_opening_skips_truncated_closures() {
+        let mut closures = [
+            closure(500, Some(20)),
+            closure(400, Some(30)),
+            closure(300, Some(60)),
+            closure(10, None),
+        ];
+        closures[0].truncated = true;
+        assert!(predict_opening(&closures, now()).is_none());
+    }
+
+    #[test]
+    fn test_hour_distance_wraps() {
+        assert_eq!(hour_distance(1, 23), 2);
+        assert_eq!(hour_distance(12, 10), 2);
+        assert_eq!(hour_distance(0, 12), 12);
+    }
+}
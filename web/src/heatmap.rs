@@ -0,0 +1,211 @@
+use crate::models::login::QueueHeatmap;
+
+const DAYS: [&str; 7] = ["MON", "TUE", "WED", "THU", "FRI", "SAT", "SUN"];
+
+const CELL_SIZE: u32 = 24;
+const CELL_GAP: u32 = 2;
+const TEXT_SCALE: u32 = 2;
+const MARGIN: u32 = 8;
+const LABEL_WIDTH: u32 = 40;
+const LABEL_HEIGHT: u32 = 16;
+const LEGEND_HEIGHT: u32 = 32;
+
+const WIDTH: u32 = MARGIN * 2 + LABEL_WIDTH + CELL_SIZE * 24;
+const HEIGHT: u32 = MARGIN * 2 + LABEL_HEIGHT + CELL_SIZE * 7 + LEGEND_HEIGHT;
+
+type Rgb = [u8; 3];
+
+const COLOR_BACKGROUND: Rgb = [0x2b, 0x2d, 0x31];
+const COLOR_TEXT: Rgb = [0xdb, 0xde, 0xe1];
+const COLOR_EMPTY: Rgb = [0x40, 0x42, 0x49];
+const COLOR_LOW: Rgb = [0x2e, 0xcc, 0x71];
+const COLOR_MID: Rgb = [0xf1, 0xc4, 0x0f];
+const COLOR_HIGH: Rgb = [0xe7, 0x4c, 0x3c];
+
+// Renders the median queue duration of each hour of the week as a PNG
+pub fn render_heatmap(heatmap: &QueueHeatmap) -> Result<Vec<u8>, png::EncodingError> {
+    let mut canvas = Canvas::new(WIDTH, HEIGHT, COLOR_BACKGROUND);
+
+    let max_duration = heatmap
+        .cells
+        .iter()
+        .map(|c| c.median_duration)
+        .fold(0f64, f64::max);
+
+    let grid_x = MARGIN + LABEL_WIDTH;
+    let grid_y = MARGIN + LABEL_HEIGHT;
+
+    for hour in 0..24 {
+        let label = hour.to_string();
+        let x = grid_x + hour * CELL_SIZE + (CELL_SIZE - text_width(&label)) / 2;
+        canvas.draw_text(x, MARGIN, &label, COLOR_TEXT);
+    }
+
+    for (day, label) in (0..).zip(DAYS) {
+        let y = grid_y + day * CELL_SIZE + (CELL_SIZE - GLYPH_HEIGHT * TEXT_SCALE) / 2;
+        canvas.draw_text(MARGIN, y, label, COLOR_TEXT);
+
+        for hour in 0..24 {
+            canvas.fill_rect(
+                grid_x + hour * CELL_SIZE,
+                grid_y + day * CELL_SIZE,
+                CELL_SIZE - CELL_GAP,
+                CELL_SIZE - CELL_GAP,
+                COLOR_EMPTY,
+            );
+        }
+    }
+
+    for cell in heatmap.cells.iter().filter(|c| c.recap_count > 0) {
+        let t = if max_duration > 0.0 {
+            cell.median_duration / max_duration
+        } else {
+            0.0
+        };
+        canvas.fill_rect(
+            grid_x + u32::from(cell.hour) * CELL_SIZE,
+            grid_y + u32::from(cell.day) * CELL_SIZE,
+            CELL_SIZE - CELL_GAP,
+            CELL_SIZE - CELL_GAP,
+            scale_color(t),
+        );
+    }
+
+    let legend_y = grid_y + CELL_SIZE * 7 + MARGIN;
+    let legend_width = CELL_SIZE * 12;
+    canvas.draw_text(
+        grid_x,
+        legend_y + (CELL_SIZE / 2 - GLYPH_HEIGHT * TEXT_SCALE) / 2,
+        "0M",
+        COLOR_TEXT,
+    );
+    let bar_x = grid_x + text_width("0M") + MARGIN;
+    for x in 0..legend_width {
+        canvas.fill_rect(
+            bar_x + x,
+            legend_y,
+            1,
+            CELL_SIZE / 2,
+            scale_color(f64::from(x) / f64::from(legend_width - 1)),
+        );
+    }
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let max_minutes = (max_duration / 60.0).ceil() as u32;
+    canvas.draw_text(
+        bar_x + legend_width + MARGIN,
+        legend_y + (CELL_SIZE / 2 - GLYPH_HEIGHT * TEXT_SCALE) / 2,
+        &format!("{max_minutes}M"),
+        COLOR_TEXT,
+    );
+
+    canvas.encode()
+}
+
+fn scale_color(t: f64) -> Rgb {
+    let t = t.clamp(0.0, 1.0);
+    if t < 0.5 {
+        lerp_color(COLOR_LOW, COLOR_MID, t * 2.0)
+    } else {
+        lerp_color(COLOR_MID, COLOR_HIGH, (t - 0.5) * 2.0)
+    }
+}
+
+fn lerp_color(a: Rgb, b: Rgb, t: f64) -> Rgb {
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let lerp = |a: u8, b: u8| (f64::from(a) + (f64::from(b) - f64::from(a)) * t).round() as u8;
+    [lerp(a[0], b[0]), lerp(a[1], b[1]), lerp(a[2], b[2])]
+}
+
+struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new(width: u32, height: u32, background: Rgb) -> Self {
+        Self {
+            width,
+            height,
+            pixels: background.repeat((width * height) as usize),
+        }
+    }
+
+    fn fill_rect(&mut self, x: u32, y: u32, width: u32, height: u32, color: Rgb) {
+        for py in y..(y + height).min(self.height) {
+            for px in x..(x + width).min(self.width) {
+                let idx = ((py * self.width + px) * 3) as usize;
+                self.pixels[idx..idx + 3].copy_from_slice(&color);
+            }
+        }
+    }
+
+    fn draw_text(&mut self, x: u32, y: u32, text: &str, color: Rgb) {
+        for (i, c) in (0..).zip(text.chars()) {
+            let glyph = glyph(c);
+            let glyph_x = x + i * (GLYPH_WIDTH + 1) * TEXT_SCALE;
+            for (row, bits) in (0..).zip(glyph) {
+                for col in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - col)) != 0 {
+                        self.fill_rect(
+                            glyph_x + col * TEXT_SCALE,
+                            y + row * TEXT_SCALE,
+                            TEXT_SCALE,
+                            TEXT_SCALE,
+                            color,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn encode(&self) -> Result<Vec<u8>, png::EncodingError> {
+        let mut data = Vec::new();
+        let mut encoder = png::Encoder::new(&mut data, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels)?;
+        writer.finish()?;
+        Ok(data)
+    }
+}
+
+const GLYPH_WIDTH: u32 = 3;
+const GLYPH_HEIGHT: u32 = 5;
+
+fn text_width(text: &str) -> u32 {
+    let len = u32::try_from(text.chars().count()).unwrap_or(u32::MAX);
+    (len * (GLYPH_WIDTH + 1) - 1) * TEXT_SCALE
+}
+
+// Minimal 3x5 bitmap font covering the characters used in the heatmap
+fn glyph(c: char) -> [u8; 5] {
+    match c {
+        '0' | 'O' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        _ => [0; 5],
+    }
+}
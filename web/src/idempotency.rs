@@ -0,0 +1,174 @@
+use actix_web::{
+    FromRequest, HttpRequest, HttpResponse,
+    dev::Payload,
+    error::{ErrorBadRequest, ErrorConflict, ErrorInternalServerError},
+    http::StatusCode,
+};
+use redis::{AsyncCommands, ExistenceCheck, RedisResult, SetExpiry, SetOptions};
+use serde::{Deserialize, Serialize};
+use std::future::{Ready, ready};
+use uuid::Uuid;
+
+use crate::storage::redis::{
+    client::RedisClient,
+    utils::{RedisKey, RedisValue},
+};
+
+const HEADER_NAME: &str = "Idempotency-Key";
+const MAX_KEY_LENGTH: usize = 128;
+// Short, so a handler that never finishes doesn't block retries for long
+const PENDING_TTL_MS: u64 = 60 * 1000;
+
+// The Idempotency-Key header, if the client sent one
+pub struct IdempotencyHeader(pub Option<String>);
+
+impl FromRequest for IdempotencyHeader {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let header = req
+            .headers()
+            .get(HEADER_NAME)
+            .map(|v| v.to_str().map(str::to_string))
+            .transpose()
+            .map_err(|_| ErrorBadRequest("Invalid idempotency key"));
+        ready(header.map(Self))
+    }
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq, Hash)]
+pub struct IdempotencyKey {
+    user_id: Uuid,
+    endpoint: &'static str,
+    key: String,
+}
+
+impl RedisKey for IdempotencyKey {
+    const PREFIX: &'static str = "idempotency";
+}
+
+impl IdempotencyKey {
+    // The header takes priority over the submission id in the body
+    pub fn new(
+        user_id: Uuid,
+        endpoint: &'static str,
+        header: IdempotencyHeader,
+        submission_id: Option<String>,
+    ) -> actix_web::Result<Option<Self>> {
+        let Some(key) = header.0.or(submission_id) else {
+            return Ok(None);
+        };
+        if key.is_empty() || key.len() > MAX_KEY_LENGTH {
+            return Err(ErrorBadRequest("Invalid idempotency key"));
+        }
+        Ok(Some(Self {
+            user_id,
+            endpoint,
+            key,
+        }))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum IdempotencyState {
+    Pending,
+    Completed { status: u16 },
+}
+
+impl RedisValue for IdempotencyState {}
+
+async fn idempotent_response_imp<F, Fut>(
+    mut redis: RedisClient,
+    key: IdempotencyKey,
+    value: F,
+) -> Result<actix_web::Result<HttpResponse>, anyhow::Error>
+where
+    F: FnOnce() -> Fut,
+    Fut: std::future::Future<Output = actix_web::Result<HttpResponse>>,
+{
+    let rkey = key.to_key(&redis)?;
+
+    let acquired: bool = redis
+        .set_options(
+            &rkey,
+            IdempotencyState::Pending.to_value()?,
+            SetOptions::default()
+                .conditional_set(ExistenceCheck::NX)
+                .with_expiration(SetExpiry::PX(PENDING_TTL_MS)),
+        )
+        .await?;
+
+    if !acquired {
+        let state: Option<Vec<u8>> = redis.get(&rkey).await?;
+        let state = state
+            .map(|s| IdempotencyState::from_value(&s))
+            .transpose()?;
+        log::info!("Duplicate submission for {:?}: {:?}", key, state);
+        return Ok(match state {
+            Some(IdempotencyState::Completed { status }) => {
+                Ok(HttpResponse::build(StatusCode::from_u16(status)?).finish())
+            }
+            Some(IdempotencyState::Pending) | None => {
+                Err(ErrorConflict("Submission is already being processed"))
+            }
+        });
+    }
+
+    let resp = value().await;
+    // The submission has already been handled, so failing to record that
+    // shouldn't fail the request. The pending marker expires on its own.
+    if let Err(e) = finish_submission(&mut redis, &rkey, &resp).await {
+        log::error!("Failed to record submission for {:?}: {}", key, e);
+    }
+    Ok(resp)
+}
+
+async fn finish_submission(
+    redis: &mut RedisClient,
+    rkey: &[u8],
+    resp: &actix_web::Result<HttpResponse>,
+) -> Result<(), anyhow::Error> {
+    match resp {
+        Ok(r) => {
+            let ttl = redis.config().idempotency_ttl_ms;
+            let r: RedisResult<()> = redis
+                .set_options(
+                    rkey,
+                    IdempotencyState::Completed {
+                        status: r.status().as_u16(),
+                    }
+                    .to_value()?,
+                    SetOptions::default().with_expiration(SetExpiry::PX(ttl)),
+                )
+                .await;
+            r?;
+        }
+        Err(_) => {
+            // Let the client retry failed submissions
+            let r: RedisResult<()> = redis.del(rkey).await;
+            r?;
+        }
+    }
+    Ok(())
+}
+
+// Runs the handler at most once per idempotency key, and replays the original
+// response for any duplicate submissions within the retention window
+pub async fn idempotent_response<F, Fut>(
+    redis: RedisClient,
+    key: Option<IdempotencyKey>,
+    value: F,
+) -> actix_web::Result<HttpResponse>
+where
+    F: FnOnce() -> Fut,
+    Fut: std::future::Future<Output = actix_web::Result<HttpResponse>>,
+{
+    let Some(key) = key else {
+        return value().await;
+    };
+    match idempotent_response_imp(redis, key, value).await {
+        Ok(r) => r,
+        Err(e) => Err(ErrorInternalServerError(e)),
+    }
+}
@@ -4,6 +4,9 @@ mod cache;
 mod config;
 mod crons;
 mod discord;
+mod estimators;
+mod heatmap;
+mod idempotency;
 mod middleware;
 mod models;
 mod natives;
@@ -12,6 +15,7 @@ mod routes;
 mod stopwatch;
 mod storage;
 mod subscriptions;
+mod validation;
 
 use crate::discord::DiscordClient;
 use ::config::{Config, Environment, File, FileFormat};
@@ -22,8 +26,9 @@ use actix_web::{
     web::Data,
 };
 use actix_web_prom::PrometheusMetricsBuilder;
+use middleware::version::ClientVersionCheck;
 use natives::version;
-use prometheus::Registry;
+use prometheus::{IntCounterVec, Opts, Registry};
 use std::io;
 use storage::redis::client::RedisClient;
 use thiserror::Error;
@@ -85,9 +90,24 @@ async fn main() -> Result<(), ServerError> {
 
     storage::game::initialize(&db_pool, &web_client).await?;
 
+    // Party estimates need to know how many of each role a duty takes
+    storage::db::duty::upsert_party_roles(
+        &db_pool,
+        storage::game::content::get_data().get_party_roles(),
+    )
+    .await
+    .expect("Error upserting party roles");
+
     let discord_bot =
         DiscordClient::new(config.discord.clone(), db_pool.clone(), redis.clone()).await;
 
+    // Before any crons start publishing
+    match discord_bot.subscriptions().migrate_redis_subscriptions().await {
+        Ok(0) => {}
+        Ok(count) => log::info!("Migrated {} subscriptions from redis", count),
+        Err(e) => log::error!("Failed to migrate subscriptions from redis: {}", e),
+    }
+
     let update_activity_token =
         crons::create_cron_job(crons::UpdateActivity::new(discord_bot.clone()));
 
@@ -100,6 +120,9 @@ async fn main() -> Result<(), ServerError> {
     let refresh_queue_estimates_token =
         crons::create_cron_job(crons::RefreshMaterializedViews::new(db_pool.clone()));
 
+    let refresh_client_version_stats_token =
+        crons::create_cron_job(crons::RefreshClientVersionStats::new(db_pool.clone()));
+
     let refresh_travel_states_token = crons::create_cron_job(
         crons::RefreshTravelStates::new(
             config.stasis.clone(),
@@ -114,8 +137,28 @@ async fn main() -> Result<(), ServerError> {
         db_pool.clone(),
     ));
 
+    let detect_error_spikes_token =
+        crons::create_cron_job(crons::DetectErrorSpikes::new(discord_bot.clone()));
+
+    let retry_subscriptions_token = crons::create_cron_job(crons::RetrySubscriptions::new(
+        discord_bot.subscriptions().clone(),
+    ));
+
+    let expire_subscriptions_token = crons::create_cron_job(crons::ExpireSubscriptions::new(
+        discord_bot.subscriptions().clone(),
+    ));
+
     let prometheus_registry = Registry::new();
 
+    let rejected_client_versions = IntCounterVec::new(
+        Opts::new(
+            "rejected_client_versions_total",
+            "Requests rejected for coming from an unsupported plugin version",
+        ),
+        &["reason"],
+    )?;
+    prometheus_registry.register(Box::new(rejected_client_versions.clone()))?;
+
     let server_prometheus = PrometheusMetricsBuilder::new("public")
         .registry(prometheus_registry.clone())
         .build()
@@ -129,6 +172,10 @@ async fn main() -> Result<(), ServerError> {
     let server = HttpServer::new(move || {
         App::new()
             .wrap(Cors::default())
+            .wrap(ClientVersionCheck::new(
+                server_config.client_versions.clone(),
+                rejected_client_versions.clone(),
+            ))
             .wrap(NormalizePath::new(TrailingSlash::Always))
             .wrap(server_prometheus.clone())
             .wrap(
@@ -184,8 +231,12 @@ async fn main() -> Result<(), ServerError> {
     let server_ret = server_task.await;
 
     refresh_queue_estimates_token.cancel();
+    refresh_client_version_stats_token.cancel();
     refresh_travel_states_token.cancel();
     refresh_world_states_token.cancel();
+    detect_error_spikes_token.cancel();
+    retry_subscriptions_token.cancel();
+    expire_subscriptions_token.cancel();
     update_stasis_token.cancel();
     update_activity_token.cancel();
     discord_bot.stop().await;
@@ -1,16 +1,38 @@
+use crate::{config::Config, storage::db};
 use actix_web::{
     dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
-    error::ErrorUnauthorized,
-    Error, HttpMessage,
+    error::{ErrorInternalServerError, ErrorUnauthorized},
+    web, Error, HttpMessage,
 };
-use actix_web_httpauth::extractors::basic::BasicAuth;
+use actix_web_httpauth::extractors::{basic::BasicAuth, bearer::BearerAuth};
 use futures_util::{future::LocalBoxFuture, FutureExt};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
 use std::{
     future::{ready, Ready},
     rc::Rc,
 };
 use uuid::Uuid;
 
+// Shared password used by installs from before per-install secrets existed
+const LEGACY_PASSWORD: &str = "🏳️‍⚧️";
+
+// Only accepted while legacy passwords are enabled in the config
+pub fn is_legacy_password(config: &Config, password: &str) -> bool {
+    config.auth.allow_legacy_password && password == LEGACY_PASSWORD
+}
+
+pub fn hash_secret(secret: &str) -> Vec<u8> {
+    Sha256::digest(secret.as_bytes()).to_vec()
+}
+
+// Returns the secret to give to the install and the hash to store
+pub fn generate_secret() -> (String, Vec<u8>) {
+    let secret = hex::encode(rand::random::<[u8; 32]>());
+    let hash = hash_secret(&secret);
+    (secret, hash)
+}
+
 pub struct BasicAuthentication;
 
 // Middleware factory is `Transform` trait
@@ -71,7 +93,24 @@ where
                 .password()
                 .ok_or(ErrorUnauthorized("No password given"))?;
 
-            if password != "🏳️‍⚧️" {
+            let pool = req
+                .app_data::<web::Data<PgPool>>()
+                .ok_or(ErrorInternalServerError("No database pool"))?;
+            let config = req
+                .app_data::<web::Data<Config>>()
+                .ok_or(ErrorInternalServerError("No config"))?;
+
+            let secret_hash = db::installs::get_install_secret_hash(pool, username)
+                .await
+                .map_err(ErrorInternalServerError)?;
+
+            let is_valid = match secret_hash {
+                Some(secret_hash) => hash_secret(password) == secret_hash,
+                // Installs that haven't registered yet
+                None => is_legacy_password(config, password),
+            };
+
+            if !is_valid {
                 return Err(ErrorUnauthorized("Invalid password"));
             }
 
@@ -82,3 +121,71 @@ where
         .boxed_local()
     }
 }
+
+pub struct AdminAuthentication;
+
+impl<S, B> Transform<S, ServiceRequest> for AdminAuthentication
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = AdminAuthMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AdminAuthMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct AdminAuthMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for AdminAuthMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let srv = self.service.clone();
+
+        async move {
+            let auth = req
+                .extract::<BearerAuth>()
+                .await
+                .ok()
+                .ok_or(ErrorUnauthorized("No credentials given"))?;
+
+            let config = req
+                .app_data::<web::Data<Config>>()
+                .ok_or(ErrorInternalServerError("No config"))?;
+
+            // Admin endpoints are disabled entirely without a configured token
+            let is_valid = config
+                .auth
+                .admin_token
+                .as_ref()
+                .is_some_and(|token| hash_secret(token) == hash_secret(auth.token()));
+
+            if !is_valid {
+                return Err(ErrorUnauthorized("Invalid token"));
+            }
+
+            srv.call(req).await
+        }
+        .boxed_local()
+    }
+}
@@ -1,6 +1,56 @@
+use crate::config::ClientVersionConfig;
 use actix_web::{
-    error::ErrorUnauthorized, http::header::USER_AGENT, Error, FromRequest, HttpRequest,
+    body::EitherBody,
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    error::ErrorUnauthorized,
+    http::{header::USER_AGENT, StatusCode},
+    Error, FromRequest, HttpRequest, HttpResponse,
 };
+use futures_util::{future::LocalBoxFuture, FutureExt};
+use prometheus::IntCounterVec;
+use serde::{Deserialize, Serialize};
+use std::{
+    future::{ready, Ready},
+    rc::Rc,
+    sync::Arc,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ClientVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl TryFrom<String> for ClientVersion {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let mut parts = value.split('.');
+        let mut next = || -> Option<u32> { parts.next()?.parse().ok() };
+        match (next(), next(), next(), next()) {
+            (Some(major), Some(minor), Some(patch), None) => Ok(Self {
+                major,
+                minor,
+                patch,
+            }),
+            _ => Err(format!("Invalid version: {value}")),
+        }
+    }
+}
+
+impl From<ClientVersion> for String {
+    fn from(value: ClientVersion) -> Self {
+        value.to_string()
+    }
+}
+
+impl std::fmt::Display for ClientVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct UserAgentVersion {
@@ -41,6 +91,14 @@ impl UserAgentVersion {
         })
     }
 
+    pub fn version(&self) -> ClientVersion {
+        ClientVersion {
+            major: self.major,
+            minor: self.minor,
+            patch: self.patch,
+        }
+    }
+
     pub fn version_cfg(&self) -> String {
         format!(
             "{major}.{minor}.{patch}-{configuration}",
@@ -87,3 +145,102 @@ impl FromRequest for UserAgentVersion {
         futures_util::future::ready(version)
     }
 }
+
+#[derive(Debug, Serialize)]
+struct UpgradeRequired {
+    error: &'static str,
+    version: ClientVersion,
+    minimum: Option<ClientVersion>,
+    maximum: Option<ClientVersion>,
+}
+
+// Refuses requests from plugin builds outside of the configured version range.
+// Requests without a Waitingway User-Agent (browsers, etc.) are let through.
+pub struct ClientVersionCheck {
+    config: Arc<ClientVersionConfig>,
+    rejections: IntCounterVec,
+}
+
+impl ClientVersionCheck {
+    pub fn new(config: ClientVersionConfig, rejections: IntCounterVec) -> Self {
+        Self {
+            config: Arc::new(config),
+            rejections,
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for ClientVersionCheck
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = ClientVersionMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(ClientVersionMiddleware {
+            service: Rc::new(service),
+            config: self.config.clone(),
+            rejections: self.rejections.clone(),
+        }))
+    }
+}
+
+pub struct ClientVersionMiddleware<S> {
+    service: Rc<S>,
+    config: Arc<ClientVersionConfig>,
+    rejections: IntCounterVec,
+}
+
+impl<S, B> Service<ServiceRequest> for ClientVersionMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let version = req
+            .headers()
+            .get(USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| UserAgentVersion::try_from(v).ok())
+            .map(|v| v.version());
+
+        if let Some(version) = version {
+            let (minimum, maximum) = self.config.range_for(req.path());
+            let too_old = minimum.is_some_and(|min| version < min);
+            let too_new = maximum.is_some_and(|max| version > max);
+            if too_old || too_new {
+                // The version comes from the client, so it's logged rather than
+                // used as a label to keep the number of series bounded
+                log::debug!("Rejected client version {} for {}", version, req.path());
+                self.rejections
+                    .with_label_values(&[if too_old { "too_old" } else { "too_new" }])
+                    .inc();
+                let resp =
+                    HttpResponse::build(StatusCode::UPGRADE_REQUIRED).json(UpgradeRequired {
+                        error: "unsupported_client_version",
+                        version,
+                        minimum,
+                        maximum,
+                    });
+                let resp = req.into_response(resp).map_into_right_body();
+                return ready(Ok(resp)).boxed_local();
+            }
+        }
+
+        let srv = self.service.clone();
+        async move { srv.call(req).await.map(ServiceResponse::map_into_left_body) }.boxed_local()
+    }
+}
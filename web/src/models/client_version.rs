@@ -0,0 +1,44 @@
+use crate::storage::db::wrappers::DatabaseDateTime;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct DbClientVersionStat {
+    pub day: Option<time::PrimitiveDateTime>,
+    pub source: Option<String>,
+    pub version: Option<String>,
+    pub configuration: Option<String>,
+    pub user_count: Option<i64>,
+    pub recap_count: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientVersionStat {
+    pub day: DatabaseDateTime,
+    // Either "login" or "duty"
+    pub source: String,
+    pub version: String,
+    // Debug or Release
+    pub configuration: String,
+    // Distinct users that sent a recap with this version on this day
+    pub user_count: i64,
+    pub recap_count: i64,
+}
+
+impl From<DbClientVersionStat> for ClientVersionStat {
+    fn from(db: DbClientVersionStat) -> Self {
+        Self {
+            day: DatabaseDateTime::from(db.day.unwrap_or(time::PrimitiveDateTime::MIN)),
+            source: db.source.unwrap_or_default(),
+            version: db.version.unwrap_or_default(),
+            configuration: db.configuration.unwrap_or_default(),
+            user_count: db.user_count.unwrap_or_default(),
+            recap_count: db.recap_count.unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ClientVersionStatsQuery {
+    // Number of days of history to return
+    pub days: Option<u32>,
+}
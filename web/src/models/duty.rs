@@ -1,7 +1,16 @@
-use super::duty_db::{DbRouletteEstimate, DbRouletteRole};
+use super::{
+    duty_db::{
+        DbContentEstimate, DbPartyEstimate, DbRoleDemand, DbRouletteEstimate,
+        DbRouletteHistoryBucket, DbRouletteRole,
+    },
+    login::EstimateConfidence,
+};
 use crate::{
     middleware::version::UserAgentVersion,
-    storage::db::wrappers::{DatabaseDateTime, DatabaseU16},
+    storage::{
+        db::wrappers::{DatabaseDateTime, DatabaseU16},
+        game::{content, get_icon_url, jobs},
+    },
 };
 use num_enum::{FromPrimitive, IntoPrimitive};
 use serde::{Deserialize, Serialize};
@@ -39,6 +48,9 @@ pub struct Recap {
 
     #[serde(skip)]
     pub client_version: UserAgentVersion,
+    // Client-generated id used to deduplicate retried submissions
+    #[serde(default, skip_serializing)]
+    pub submission_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -152,6 +164,58 @@ pub struct PartyMakeup {
     pub members: Vec<PartyMember>,
 }
 
+impl PartyMakeup {
+    // None if any member's job doesn't have a role
+    pub fn composition(&self) -> Option<PartyComposition> {
+        let job_data = jobs::get_data();
+        let roles = self
+            .members
+            .iter()
+            .map(|m| {
+                u8::try_from(m.job.0)
+                    .ok()
+                    .and_then(|id| job_data.get_job_by_id(id))
+                    .and_then(|j| j.role)
+            })
+            .collect::<Option<Vec<_>>>()?;
+        PartyComposition::from_roles(&roles)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartyComposition {
+    pub member_count: u8,
+    pub tanks: u8,
+    pub healers: u8,
+    pub dps: u8,
+}
+
+impl PartyComposition {
+    // What's still needed is up to the queued content's party roles, which party_estimates joins in
+    pub fn from_roles(roles: &[RouletteRole]) -> Option<Self> {
+        if roles.is_empty() || roles.len() > 8 {
+            return None;
+        }
+        let member_count = u8::try_from(roles.len()).ok()?;
+        let count =
+            |role: RouletteRole| u8::try_from(roles.iter().filter(|&&r| r == role).count()).ok();
+        Some(Self {
+            member_count,
+            tanks: count(RouletteRole::Tank)?,
+            healers: count(RouletteRole::Healer)?,
+            dps: count(RouletteRole::Dps)?,
+        })
+    }
+}
+
+// Role slots in a single party of a roulette or duty
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartyRoles {
+    pub tanks: u8,
+    pub healers: u8,
+    pub dps: u8,
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type)]
 #[sqlx(type_name = "duty_party_member")]
 pub struct PartyMember {
@@ -206,7 +270,6 @@ impl ContentFlags {
             | (languages << 9)
     }
 
-    #[allow(dead_code)]
     pub fn from_flags(flags: u16) -> (Self, QueueLanguage) {
         let is_unrestricted = (flags & 1) != 0;
         let is_min_ilvl = (flags & 2) != 0;
@@ -279,11 +342,14 @@ pub enum LootRule {
     Lootmaster = 2,
 }
 
-#[derive(Debug, Copy, Clone, Serialize_repr, Deserialize_repr)]
+#[derive(
+    Debug, Copy, Clone, PartialEq, Eq, Serialize_repr, Deserialize_repr, poise::ChoiceParameter,
+)]
 #[repr(u8)]
 pub enum RouletteRole {
     Tank = 1,
     Healer = 2,
+    #[name = "DPS"]
     Dps = 3,
 }
 
@@ -321,6 +387,9 @@ pub struct RouletteSize {
 
     pub size: Option<RoulettePosition>,
     pub estimated_wait_time: Option<WaitTime>,
+    // Client-generated id used to deduplicate retried submissions
+    #[serde(default, skip_serializing)]
+    pub submission_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -331,7 +400,10 @@ pub struct RouletteEstimate {
     pub role: RouletteRole,
 
     pub last_update: DatabaseDateTime,
-    pub wait_time: f64,
+    pub wait_time: Option<f64>,
+    // When the wait time itself was reported, which can be older than last_update
+    #[serde(skip)]
+    pub wait_time_update: Option<DatabaseDateTime>,
     pub size: RoulettePosition,
     pub estimated_wait_time: WaitTime,
 }
@@ -344,7 +416,8 @@ impl From<DbRouletteEstimate> for RouletteEstimate {
             roulette_id: (db.roulette_id as u16).try_into().unwrap_or_default(),
             role: db.role.into(),
             last_update: db.time.unwrap_or_default(),
-            wait_time: db.duration.unwrap_or_default(),
+            wait_time: db.duration,
+            wait_time_update: db.duration_time,
             size: u8::try_from(db.size.unwrap_or_default() as u16)
                 .unwrap_or_default()
                 .into(),
@@ -354,3 +427,287 @@ impl From<DbRouletteEstimate> for RouletteEstimate {
         }
     }
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouletteHistory {
+    pub roulette_id: u8,
+    pub role: RouletteRole,
+    // Change in the median wait time, in seconds per day
+    pub trend: Option<f64>,
+    pub buckets: Vec<RouletteHistoryBucket>,
+}
+
+impl RouletteHistory {
+    pub fn new(roulette_id: u8, role: RouletteRole, buckets: Vec<RouletteHistoryBucket>) -> Self {
+        Self {
+            roulette_id,
+            role,
+            trend: Self::calculate_trend(&buckets),
+            buckets,
+        }
+    }
+
+    // Least squares fit of the median wait time over time, weighted by the
+    // number of recaps in each bucket
+    #[allow(clippy::cast_precision_loss)]
+    fn calculate_trend(buckets: &[RouletteHistoryBucket]) -> Option<f64> {
+        if buckets.len() < 2 {
+            return None;
+        }
+
+        let origin = buckets.first()?.time.0;
+        let points = buckets
+            .iter()
+            .map(|b| {
+                (
+                    (b.time.0 - origin).as_seconds_f64() / 86400.0,
+                    b.median_wait_time,
+                    b.recap_count as f64,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let total_weight: f64 = points.iter().map(|(_, _, w)| w).sum();
+        let mean_x = points.iter().map(|(x, _, w)| x * w).sum::<f64>() / total_weight;
+        let mean_y = points.iter().map(|(_, y, w)| y * w).sum::<f64>() / total_weight;
+
+        let covariance: f64 = points
+            .iter()
+            .map(|(x, y, w)| w * (x - mean_x) * (y - mean_y))
+            .sum();
+        let variance: f64 = points
+            .iter()
+            .map(|(x, _, w)| w * (x - mean_x).powi(2))
+            .sum();
+
+        (variance > 0.0).then(|| covariance / variance)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouletteHistoryBucket {
+    // Start of the bucket
+    pub time: DatabaseDateTime,
+    // Median time from queueing to the first pop, in seconds
+    pub median_wait_time: f64,
+    // Median of the last wait time shown in game before the pop, in seconds
+    pub median_estimated_wait_time: Option<f64>,
+    pub recap_count: i64,
+}
+
+impl From<DbRouletteHistoryBucket> for RouletteHistoryBucket {
+    fn from(db: DbRouletteHistoryBucket) -> Self {
+        Self {
+            time: db.time,
+            median_wait_time: db.duration.unwrap_or_default(),
+            median_estimated_wait_time: db.estimated_duration,
+            recap_count: db.count,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContentEstimate {
+    pub datacenter_id: u16,
+    pub languages: QueueLanguage,
+    pub content_id: u16,
+    pub role: RouletteRole,
+
+    pub name: String,
+    pub image_url: String,
+
+    pub last_update: DatabaseDateTime,
+    // Statistics over all solo queues for the duty in the last 30 days
+    pub p50_wait_time: f64,
+    pub p90_wait_time: f64,
+    pub sample_count: i64,
+    pub confidence: EstimateConfidence,
+}
+
+impl From<DbContentEstimate> for ContentEstimate {
+    fn from(db: DbContentEstimate) -> Self {
+        let content_id = db.content_id.unwrap_or_default() as u16;
+        let sample_count = db.sample_count.unwrap_or_default();
+        let content_data = content::get_data();
+        Self {
+            datacenter_id: db.datacenter_id.unwrap_or_default() as u16,
+            languages: u8::try_from(db.languages.unwrap_or_default() as u16)
+                .unwrap_or_default()
+                .into(),
+            content_id,
+            role: db.role.into(),
+            name: content_data.get_content_name(content_id),
+            image_url: get_icon_url(&content_data.get_content_image(content_id)),
+            last_update: db.time.unwrap_or_default(),
+            p50_wait_time: db.duration_p50.unwrap_or_default(),
+            p90_wait_time: db.duration_p90.unwrap_or_default(),
+            sample_count,
+            confidence: EstimateConfidence::from_sample_count(sample_count),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartyEstimate {
+    pub datacenter_id: u16,
+    pub languages: QueueLanguage,
+    pub roulette_id: Option<u8>,
+    pub content_id: Option<u16>,
+
+    // Roles the party still needed to fill from the duty finder
+    pub tanks_needed: u8,
+    pub healers_needed: u8,
+    pub dps_needed: u8,
+
+    pub last_update: DatabaseDateTime,
+    // Statistics over all party queues with this makeup in the last 30 days
+    pub p50_wait_time: f64,
+    pub p90_wait_time: f64,
+    pub sample_count: i64,
+    pub confidence: EstimateConfidence,
+}
+
+impl From<DbPartyEstimate> for PartyEstimate {
+    fn from(db: DbPartyEstimate) -> Self {
+        let sample_count = db.sample_count.unwrap_or_default();
+        Self {
+            datacenter_id: db.datacenter_id.unwrap_or_default() as u16,
+            languages: u8::try_from(db.languages.unwrap_or_default() as u16)
+                .unwrap_or_default()
+                .into(),
+            roulette_id: db
+                .roulette_id
+                .filter(|&id| id != 0)
+                .and_then(|id| u8::try_from(id).ok()),
+            content_id: db.content_id.filter(|&id| id != 0).map(|id| id as u16),
+            tanks_needed: u8::try_from(db.tanks_needed.unwrap_or_default()).unwrap_or_default(),
+            healers_needed: u8::try_from(db.healers_needed.unwrap_or_default()).unwrap_or_default(),
+            dps_needed: u8::try_from(db.dps_needed.unwrap_or_default()).unwrap_or_default(),
+            last_update: db.time.unwrap_or_default(),
+            p50_wait_time: db.duration_p50.unwrap_or_default(),
+            p90_wait_time: db.duration_p90.unwrap_or_default(),
+            sample_count,
+            confidence: EstimateConfidence::from_sample_count(sample_count),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoleDemand {
+    pub datacenter_id: u16,
+    pub role: RouletteRole,
+
+    // Roulette wait time of the role relative to the average of all roles in the
+    // same roulette. Below 1 means the role gets in faster than the others.
+    pub relative_wait: Option<f64>,
+    pub wait_sample_count: i64,
+    // Average fraction of the role's slots that were already filled while in
+    // queue. Lower means the role is harder to find.
+    pub fill_rate: Option<f64>,
+    pub fill_sample_count: i64,
+}
+
+impl From<DbRoleDemand> for RoleDemand {
+    fn from(db: DbRoleDemand) -> Self {
+        Self {
+            datacenter_id: db.datacenter_id as u16,
+            role: db.role.into(),
+            relative_wait: db.relative_wait,
+            wait_sample_count: db.wait_sample_count.unwrap_or_default(),
+            fill_rate: db.fill_rate,
+            fill_sample_count: db.fill_sample_count.unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history_bucket(day: i64, median_wait_time: f64, recap_count: i64) -> RouletteHistoryBucket {
+        RouletteHistoryBucket {
+            time: (time::OffsetDateTime::UNIX_EPOCH + time::Duration::days(day)).into(),
+            median_wait_time,
+            median_estimated_wait_time: None,
+            recap_count,
+        }
+    }
+
+    #[test]
+    fn test_roulette_history_trend() {
+        let buckets = [
+            history_bucket(0, 600.0, 10),
+            history_bucket(1, 660.0, 10),
+            history_bucket(2, 720.0, 10),
+        ];
+        let trend = RouletteHistory::calculate_trend(&buckets).unwrap();
+        assert!((trend - 60.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_roulette_history_trend_weights_by_recap_count() {
+        // The outlier barely has any recaps, so it pulls the trend up far less
+        // than it would if every bucket counted the same
+        let weighted = RouletteHistory::calculate_trend(&[
+            history_bucket(0, 600.0, 100),
+            history_bucket(1, 600.0, 100),
+            history_bucket(2, 6000.0, 1),
+        ])
+        .unwrap();
+        let unweighted = RouletteHistory::calculate_trend(&[
+            history_bucket(0, 600.0, 1),
+            history_bucket(1, 600.0, 1),
+            history_bucket(2, 6000.0, 1),
+        ])
+        .unwrap();
+        assert!(weighted > 0.0);
+        assert!(weighted < unweighted / 10.0);
+    }
+
+    #[test]
+    fn test_roulette_history_trend_needs_two_buckets() {
+        assert_eq!(RouletteHistory::calculate_trend(&[]), None);
+        assert_eq!(
+            RouletteHistory::calculate_trend(&[history_bucket(0, 600.0, 10)]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_party_composition_counts_roles() {
+        let composition = PartyComposition::from_roles(&[
+            RouletteRole::Tank,
+            RouletteRole::Dps,
+            RouletteRole::Dps,
+        ])
+        .unwrap();
+
+        assert_eq!(
+            composition,
+            PartyComposition {
+                member_count: 3,
+                tanks: 1,
+                healers: 0,
+                dps: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn test_party_composition_ignores_party_size() {
+        // A premade of two is the same composition no matter what size of duty it queues for
+        let composition =
+            PartyComposition::from_roles(&[RouletteRole::Healer, RouletteRole::Healer]).unwrap();
+
+        assert_eq!(composition.member_count, 2);
+        assert_eq!(composition.tanks, 0);
+        assert_eq!(composition.healers, 2);
+        assert_eq!(composition.dps, 0);
+    }
+
+    #[test]
+    fn test_party_composition_rejects_invalid_sizes() {
+        assert_eq!(PartyComposition::from_roles(&[]), None);
+        assert_eq!(PartyComposition::from_roles(&[RouletteRole::Dps; 9]), None);
+    }
+}
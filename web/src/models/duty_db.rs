@@ -41,7 +41,58 @@ pub struct DbRouletteEstimate {
     pub role: DbRouletteRole,
 
     pub time: Option<DatabaseDateTime>,
+    pub duration_time: Option<DatabaseDateTime>,
     pub duration: Option<f64>,
     pub size: Option<i16>,
     pub wait_time: Option<i16>,
 }
+
+#[derive(Debug, FromRow)]
+pub struct DbRouletteHistoryBucket {
+    pub roulette_id: i16,
+    pub role: DbRouletteRole,
+    pub time: DatabaseDateTime,
+    pub duration: Option<f64>,
+    pub estimated_duration: Option<f64>,
+    pub count: i64,
+}
+
+#[derive(Debug, FromRow)]
+pub struct DbContentEstimate {
+    pub datacenter_id: Option<i16>,
+    pub languages: Option<i16>,
+    pub content_id: Option<i16>,
+    pub role: DbRouletteRole,
+
+    pub time: Option<DatabaseDateTime>,
+    pub duration_p50: Option<f64>,
+    pub duration_p90: Option<f64>,
+    pub sample_count: Option<i64>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct DbPartyEstimate {
+    pub datacenter_id: Option<i16>,
+    pub languages: Option<i16>,
+    pub roulette_id: Option<i16>,
+    pub content_id: Option<i16>,
+    pub tanks_needed: Option<i16>,
+    pub healers_needed: Option<i16>,
+    pub dps_needed: Option<i16>,
+
+    pub time: Option<DatabaseDateTime>,
+    pub duration_p50: Option<f64>,
+    pub duration_p90: Option<f64>,
+    pub sample_count: Option<i64>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct DbRoleDemand {
+    pub datacenter_id: i16,
+    pub role: DbRouletteRole,
+
+    pub relative_wait: Option<f64>,
+    pub wait_sample_count: Option<i64>,
+    pub fill_rate: Option<f64>,
+    pub fill_sample_count: Option<i64>,
+}
@@ -0,0 +1,132 @@
+use crate::storage::{db::wrappers::DatabaseDateTime, game::content};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Debug, Deserialize)]
+pub struct OutcomeQuery {
+    pub roulette_id: Option<Vec<u8>>,
+    // Number of days of history to aggregate
+    pub days: Option<u32>,
+}
+
+impl OutcomeQuery {
+    pub const DEFAULT_DAYS: u32 = 30;
+    pub const MAX_DAYS: u32 = 90;
+}
+
+#[derive(Debug, FromRow)]
+pub struct DbPopOutcome {
+    pub roulette_id: i16,
+    pub content_id: Option<i16>,
+    pub pop_count: i64,
+    pub in_progress_count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouletteOutcomes {
+    pub roulette_id: u8,
+    pub name: String,
+    pub pop_count: i64,
+    // Fraction of pops that joined a party already in the duty
+    pub in_progress_rate: f64,
+    // Sorted by most common first
+    pub content: Vec<ContentOutcome>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContentOutcome {
+    // None if the client didn't know which duty it popped into
+    pub content_id: Option<u16>,
+    pub name: String,
+    pub pop_count: i64,
+    // Fraction of the roulette's pops that landed on this duty
+    pub rate: f64,
+    pub in_progress_rate: f64,
+}
+
+impl RouletteOutcomes {
+    #[allow(clippy::cast_precision_loss)]
+    pub fn new(roulette_id: u8, outcomes: Vec<DbPopOutcome>) -> Self {
+        let content_data = content::get_data();
+        let pop_count: i64 = outcomes.iter().map(|o| o.pop_count).sum();
+        let in_progress_count: i64 = outcomes.iter().map(|o| o.in_progress_count).sum();
+        let rate = |count: i64, total: i64| {
+            if total > 0 {
+                count as f64 / total as f64
+            } else {
+                0.0
+            }
+        };
+
+        let mut content = outcomes
+            .into_iter()
+            .map(|o| {
+                let content_id = o.content_id.map(|id| id as u16);
+                ContentOutcome {
+                    content_id,
+                    name: content_id.map_or_else(
+                        || "Unknown".to_string(),
+                        |id| content_data.get_content_name(id),
+                    ),
+                    pop_count: o.pop_count,
+                    rate: rate(o.pop_count, pop_count),
+                    in_progress_rate: rate(o.in_progress_count, o.pop_count),
+                }
+            })
+            .collect::<Vec<_>>();
+        content.sort_unstable_by_key(|c| std::cmp::Reverse(c.pop_count));
+
+        Self {
+            roulette_id,
+            name: content_data.get_roulette_name(roulette_id),
+            pop_count,
+            in_progress_rate: rate(in_progress_count, pop_count),
+            content,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WithdrawQuery {
+    pub roulette_id: Option<Vec<u8>>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct DbWithdrawCount {
+    pub roulette_id: Option<i16>,
+    pub time: DatabaseDateTime,
+    pub withdraw_message: i16,
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WithdrawHistory {
+    // None for queues for specific duties
+    pub roulette_id: Option<u8>,
+    pub buckets: Vec<WithdrawBucket>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WithdrawBucket {
+    // Start of the bucket
+    pub time: DatabaseDateTime,
+    pub reasons: Vec<WithdrawReason>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WithdrawReason {
+    pub message_id: u16,
+    pub reason: String,
+    pub count: i64,
+}
+
+impl From<DbWithdrawCount> for WithdrawReason {
+    fn from(db: DbWithdrawCount) -> Self {
+        let message_id = db.withdraw_message as u16;
+        Self {
+            message_id,
+            reason: content::get_data().get_withdraw_reason(message_id),
+            count: db.count,
+        }
+    }
+}
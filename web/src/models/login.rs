@@ -36,6 +36,10 @@ pub struct Recap {
     #[sqlx(skip)]
     #[serde(skip)]
     pub client_version: UserAgentVersion,
+    // Client-generated id used to deduplicate retried submissions
+    #[sqlx(skip)]
+    #[serde(default, skip_serializing)]
+    pub submission_id: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
@@ -68,6 +72,10 @@ pub struct QueueSize {
     pub world_id: DatabaseU16,
     // Size of the queue
     pub size: i32,
+    // Client-generated id used to deduplicate retried submissions
+    #[sqlx(skip)]
+    #[serde(default, skip_serializing)]
+    pub submission_id: Option<String>,
 }
 
 // sqlx throws a fit and doesn't accept sqlx(rename = "") nor Option<DatabaseU16>
@@ -78,6 +86,10 @@ pub struct DbQueueEstimate {
     pub time: Option<time::PrimitiveDateTime>,
     pub size: Option<i32>,
     pub duration: Option<f64>,
+
+    pub duration_p50: Option<f64>,
+    pub duration_p90: Option<f64>,
+    pub sample_count: Option<i64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -87,15 +99,249 @@ pub struct QueueEstimate {
     pub last_update: DatabaseDateTime,
     pub last_size: i32,
     pub last_duration: f64,
+
+    // Statistics over all successful queues in the last 2 hours
+    pub p50_duration: f64,
+    pub p90_duration: f64,
+    pub sample_count: i64,
+    pub confidence: EstimateConfidence,
 }
 
 impl From<DbQueueEstimate> for QueueEstimate {
     fn from(db: DbQueueEstimate) -> Self {
+        let sample_count = db.sample_count.unwrap_or_default();
         Self {
             world_id: db.world_id.unwrap_or_default() as u16,
             last_update: DatabaseDateTime::from(db.time.unwrap_or(time::PrimitiveDateTime::MIN)),
             last_size: db.size.unwrap_or_default(),
             last_duration: db.duration.unwrap_or_default(),
+
+            p50_duration: db.duration_p50.unwrap_or_default(),
+            p90_duration: db.duration_p90.unwrap_or_default(),
+            sample_count,
+            confidence: EstimateConfidence::from_sample_count(sample_count),
+        }
+    }
+}
+
+impl QueueEstimate {
+    // The median duration if there's recent data, otherwise the last recorded duration
+    pub fn typical_duration(&self) -> f64 {
+        if self.sample_count == 0 {
+            self.last_duration
+        } else {
+            self.p50_duration
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EstimateConfidence {
+    None,
+    Low,
+    Medium,
+    High,
+}
+
+impl EstimateConfidence {
+    pub fn from_sample_count(sample_count: i64) -> Self {
+        match sample_count {
+            ..=0 => Self::None,
+            1..5 => Self::Low,
+            5..20 => Self::Medium,
+            _ => Self::High,
+        }
+    }
+}
+
+impl std::fmt::Display for EstimateConfidence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::None => write!(f, "No"),
+            Self::Low => write!(f, "Low"),
+            Self::Medium => write!(f, "Medium"),
+            Self::High => write!(f, "High"),
+        }
+    }
+}
+
+#[derive(Debug, FromRow)]
+pub struct DbQueueHistoryBucket {
+    pub world_id: i16,
+    pub time: DatabaseDateTime,
+    pub duration: Option<f64>,
+    pub max_position: Option<i32>,
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueHistory {
+    pub world_id: u16,
+    pub buckets: Vec<QueueHistoryBucket>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueHistoryBucket {
+    // Start of the bucket
+    pub time: DatabaseDateTime,
+    // Median duration of all successful queues started in the bucket
+    pub median_duration: f64,
+    // Largest position seen in the bucket
+    pub max_position: i32,
+    pub recap_count: i64,
+}
+
+impl From<DbQueueHistoryBucket> for QueueHistoryBucket {
+    fn from(db: DbQueueHistoryBucket) -> Self {
+        Self {
+            time: db.time,
+            median_duration: db.duration.unwrap_or_default(),
+            max_position: db.max_position.unwrap_or_default(),
+            recap_count: db.count,
+        }
+    }
+}
+
+#[derive(Debug, FromRow)]
+pub struct DbQueueThroughput {
+    pub world_id: Option<i16>,
+
+    pub time: Option<time::PrimitiveDateTime>,
+    pub throughput: Option<f64>,
+    pub sample_count: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueThroughput {
+    pub world_id: u16,
+
+    pub last_update: DatabaseDateTime,
+    // Positions cleared per minute
+    pub throughput: f64,
+    // Number of recaps the throughput was fit from
+    pub sample_count: i64,
+}
+
+impl From<DbQueueThroughput> for QueueThroughput {
+    fn from(db: DbQueueThroughput) -> Self {
+        Self {
+            world_id: db.world_id.unwrap_or_default() as u16,
+            last_update: DatabaseDateTime::from(db.time.unwrap_or(time::PrimitiveDateTime::MIN)),
+            throughput: db.throughput.unwrap_or_default(),
+            sample_count: db.sample_count.unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueWaitEstimate {
+    pub world_id: u16,
+    pub position: u32,
+
+    // Estimated time until login, in seconds
+    pub duration: f64,
+    pub throughput: f64,
+    pub sample_count: i64,
+}
+
+#[derive(Debug, FromRow)]
+pub struct DbErrorCount {
+    pub world_id: i16,
+    pub time: DatabaseDateTime,
+    pub error_code: i32,
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorCount {
+    pub world_id: u16,
+    // Start of the bucket
+    pub time: DatabaseDateTime,
+    pub error_code: i32,
+    pub count: i64,
+}
+
+impl From<DbErrorCount> for ErrorCount {
+    fn from(db: DbErrorCount) -> Self {
+        Self {
+            world_id: DatabaseU16::from(db.world_id).0,
+            time: db.time,
+            error_code: db.error_code,
+            count: db.count,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DatacenterErrorRate {
+    pub datacenter_id: u16,
+    // Number of queues that ended in the window
+    pub total: i64,
+    // Number of those queues that ended with an error
+    pub errors: i64,
+    // Error codes and their counts, most common first
+    pub codes: Vec<(i32, i64)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ErrorCodeWindow {
+    pub datacenter_id: u16,
+    pub error_code: i32,
+    pub recent_count: i64,
+    pub baseline_count: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HeatmapQuery {
+    pub world_id: u16,
+    // IANA time zone name, defaults to UTC
+    pub tz: Option<String>,
+    // Number of days of history to aggregate
+    pub days: Option<u32>,
+}
+
+impl HeatmapQuery {
+    pub const DEFAULT_TIMEZONE: &str = "UTC";
+    pub const DEFAULT_DAYS: u32 = 28;
+    pub const MAX_DAYS: u32 = 90;
+}
+
+#[derive(Debug, FromRow)]
+pub struct DbQueueHeatmapCell {
+    pub day: i32,
+    pub hour: i32,
+    pub duration: Option<f64>,
+    pub size: Option<f64>,
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueHeatmap {
+    pub world_id: u16,
+    pub timezone: String,
+    pub cells: Vec<QueueHeatmapCell>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueHeatmapCell {
+    // Day of the week, 0 = Monday
+    pub day: u8,
+    // Hour of the day, 0-23
+    pub hour: u8,
+    pub median_duration: f64,
+    pub median_size: f64,
+    pub recap_count: i64,
+}
+
+impl From<DbQueueHeatmapCell> for QueueHeatmapCell {
+    fn from(db: DbQueueHeatmapCell) -> Self {
+        Self {
+            day: db.day as u8,
+            hour: db.hour as u8,
+            median_duration: db.duration.unwrap_or_default(),
+            median_size: db.size.unwrap_or_default(),
+            recap_count: db.count,
         }
     }
 }
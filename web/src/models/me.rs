@@ -0,0 +1,377 @@
+use super::{
+    duty::{ContentFlags, QueueLanguage, RecapPop, RouletteRole},
+    duty_db::DbRouletteRole,
+    login::{RecapError, RecapPosition},
+    Connection,
+};
+use crate::storage::{
+    db::wrappers::{DatabaseDateTime, DatabaseU16},
+    game::content,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+pub struct RecapHistoryQuery {
+    pub world_id: Option<Vec<u16>>,
+    pub start: Option<DatabaseDateTime>,
+    pub end: Option<DatabaseDateTime>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+#[derive(Debug)]
+pub struct RecapFilter {
+    pub world_ids: Option<Vec<u16>>,
+    pub start: Option<DatabaseDateTime>,
+    pub end: Option<DatabaseDateTime>,
+    // None returns every matching recap
+    pub limit: Option<u32>,
+    pub offset: u32,
+}
+
+impl RecapHistoryQuery {
+    const DEFAULT_LIMIT: u32 = 50;
+    const MAX_LIMIT: u32 = 200;
+
+    pub fn resolve(self) -> Result<RecapFilter, &'static str> {
+        if let (Some(start), Some(end)) = (self.start, self.end) {
+            if start >= end {
+                return Err("start must be before end");
+            }
+        }
+        let limit = self.limit.unwrap_or(Self::DEFAULT_LIMIT);
+        if limit == 0 || limit > Self::MAX_LIMIT {
+            return Err("Invalid limit");
+        }
+
+        Ok(RecapFilter {
+            world_ids: self.world_id,
+            start: self.start,
+            end: self.end,
+            limit: Some(limit),
+            offset: self.offset.unwrap_or_default(),
+        })
+    }
+}
+
+impl RecapFilter {
+    pub fn all() -> Self {
+        Self {
+            world_ids: None,
+            start: None,
+            end: None,
+            limit: None,
+            offset: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecapPage<T> {
+    pub recaps: Vec<T>,
+    // Number of recaps matching the filters, ignoring pagination
+    pub total: i64,
+}
+
+#[derive(Debug, FromRow)]
+pub struct DbLoginRecap {
+    pub id: Uuid,
+    pub world_id: i16,
+    pub free_trial: bool,
+    pub successful: bool,
+    pub reentered: bool,
+    pub error_type: Option<i32>,
+    pub error_code: Option<i32>,
+    pub error_info: Option<String>,
+    pub error_row: Option<i16>,
+    pub start_time: DatabaseDateTime,
+    pub end_time: DatabaseDateTime,
+    pub end_identify_time: Option<DatabaseDateTime>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct DbLoginRecapPosition {
+    pub recap_id: Uuid,
+    pub time: DatabaseDateTime,
+    pub identify_time: Option<DatabaseDateTime>,
+    pub position: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LoginRecap {
+    pub id: Uuid,
+    pub world_id: u16,
+    pub free_trial: bool,
+    pub successful: bool,
+    pub reentered: bool,
+    pub error: Option<RecapError>,
+    pub start_time: DatabaseDateTime,
+    pub end_time: DatabaseDateTime,
+    pub end_identify_time: Option<DatabaseDateTime>,
+    pub positions: Vec<RecapPosition>,
+}
+
+impl LoginRecap {
+    pub fn new(db: DbLoginRecap, positions: Vec<RecapPosition>) -> Self {
+        let error = match (db.error_type, db.error_code, db.error_info, db.error_row) {
+            (Some(r#type), Some(code), Some(info), Some(error_row)) => Some(RecapError {
+                r#type,
+                code,
+                info,
+                error_row: DatabaseU16::from(error_row),
+            }),
+            _ => None,
+        };
+        Self {
+            id: db.id,
+            world_id: DatabaseU16::from(db.world_id).0,
+            free_trial: db.free_trial,
+            successful: db.successful,
+            reentered: db.reentered,
+            error,
+            start_time: db.start_time,
+            end_time: db.end_time,
+            end_identify_time: db.end_identify_time,
+            positions,
+        }
+    }
+}
+
+impl From<DbLoginRecapPosition> for RecapPosition {
+    fn from(db: DbLoginRecapPosition) -> Self {
+        Self {
+            time: db.time,
+            identify_time: db.identify_time,
+            position: db.position,
+        }
+    }
+}
+
+#[derive(Debug, FromRow)]
+pub struct DbDutyRecap {
+    pub id: Uuid,
+    pub queued_roulette: Option<i16>,
+    pub queued_content: Option<Vec<i16>>,
+    pub queued_job: i16,
+    pub queued_flags: i16,
+    pub world_id: i16,
+    pub is_party_leader: bool,
+    pub start_time: DatabaseDateTime,
+    pub end_time: DatabaseDateTime,
+    pub withdraw_message: Option<i16>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct DbDutyRecapPop {
+    pub recap_id: Uuid,
+    pub time: DatabaseDateTime,
+    pub flags: i16,
+    pub content: Option<i16>,
+    pub in_progress_time: Option<DatabaseDateTime>,
+}
+
+// Queue updates are left out; they're only useful for the aggregate estimates
+#[derive(Debug, Serialize)]
+pub struct DutyRecap {
+    pub id: Uuid,
+    pub queued_roulette: Option<u8>,
+    pub queued_content: Option<Vec<u16>>,
+    pub queued_job: u8,
+    pub queued_flags: ContentFlags,
+    pub queued_languages: QueueLanguage,
+    pub world_id: u16,
+    pub is_party_leader: bool,
+    pub start_time: DatabaseDateTime,
+    pub end_time: DatabaseDateTime,
+    pub withdraw_message: Option<u16>,
+    pub withdraw_reason: Option<String>,
+    pub pops: Vec<RecapPop>,
+}
+
+impl DutyRecap {
+    #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+    pub fn new(db: DbDutyRecap, pops: Vec<RecapPop>) -> Self {
+        let (queued_flags, queued_languages) =
+            ContentFlags::from_flags(DatabaseU16::from(db.queued_flags).0);
+        let withdraw_message = db.withdraw_message.map(|m| DatabaseU16::from(m).0);
+        Self {
+            id: db.id,
+            queued_roulette: db.queued_roulette.map(|r| r as u8),
+            queued_content: db
+                .queued_content
+                .map(|c| c.into_iter().map(|c| DatabaseU16::from(c).0).collect()),
+            queued_job: db.queued_job as u8,
+            queued_flags,
+            queued_languages,
+            world_id: DatabaseU16::from(db.world_id).0,
+            is_party_leader: db.is_party_leader,
+            start_time: db.start_time,
+            end_time: db.end_time,
+            withdraw_message,
+            withdraw_reason: withdraw_message.map(|m| content::get_data().get_withdraw_reason(m)),
+            pops,
+        }
+    }
+}
+
+impl From<DbDutyRecapPop> for RecapPop {
+    fn from(db: DbDutyRecapPop) -> Self {
+        Self {
+            time: db.time,
+            resulting_flags: ContentFlags::from_flags(DatabaseU16::from(db.flags).0).0,
+            resulting_content: db.content.map(|c| DatabaseU16::from(c).0),
+            in_progress_time: db.in_progress_time,
+        }
+    }
+}
+
+#[derive(Debug, FromRow)]
+pub struct DbRecapStats {
+    pub recap_count: i64,
+    pub successful_count: i64,
+    pub total_queue_time: f64,
+    pub longest_queue_time: Option<f64>,
+    pub longest_queue_id: Option<Uuid>,
+    pub first_queue: Option<DatabaseDateTime>,
+    pub last_queue: Option<DatabaseDateTime>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecapStats {
+    pub recap_count: i64,
+    // Login: made it into the world, Duty: popped without being withdrawn
+    pub successful_count: i64,
+    // In seconds
+    pub total_queue_time: f64,
+    pub longest_queue: Option<LongestQueue>,
+    pub first_queue: Option<DatabaseDateTime>,
+    pub last_queue: Option<DatabaseDateTime>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LongestQueue {
+    pub recap_id: Uuid,
+    // In seconds
+    pub duration: f64,
+}
+
+impl From<DbRecapStats> for RecapStats {
+    fn from(db: DbRecapStats) -> Self {
+        Self {
+            recap_count: db.recap_count,
+            successful_count: db.successful_count,
+            total_queue_time: db.total_queue_time,
+            longest_queue: db
+                .longest_queue_id
+                .zip(db.longest_queue_time)
+                .map(|(recap_id, duration)| LongestQueue { recap_id, duration }),
+            first_queue: db.first_queue,
+            last_queue: db.last_queue,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UserStats {
+    pub login: RecapStats,
+    pub duty: RecapStats,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct InstallInfo {
+    pub created_at: DatabaseDateTime,
+    pub rotated_at: DatabaseDateTime,
+}
+
+#[derive(Debug, FromRow)]
+pub struct DbQueueSizeContribution {
+    pub world_id: i16,
+    pub time: DatabaseDateTime,
+    pub size: i32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueSizeContribution {
+    pub world_id: u16,
+    pub time: DatabaseDateTime,
+    pub size: i32,
+}
+
+impl From<DbQueueSizeContribution> for QueueSizeContribution {
+    fn from(db: DbQueueSizeContribution) -> Self {
+        Self {
+            world_id: DatabaseU16::from(db.world_id).0,
+            time: db.time,
+            size: db.size,
+        }
+    }
+}
+
+// Only the values that were last reported by the user are filled in
+#[derive(Debug, FromRow)]
+pub struct DbRouletteSizeContribution {
+    pub datacenter_id: i16,
+    pub languages: i16,
+    pub roulette_id: i16,
+    pub role: DbRouletteRole,
+    pub size_time: Option<DatabaseDateTime>,
+    pub size: Option<i16>,
+    pub est_time_time: Option<DatabaseDateTime>,
+    pub est_time: Option<i16>,
+    pub wait_time_time: Option<DatabaseDateTime>,
+    pub wait_time: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RouletteSizeContribution {
+    pub datacenter_id: u16,
+    pub languages: QueueLanguage,
+    pub roulette_id: u8,
+    pub role: RouletteRole,
+    pub size_time: Option<DatabaseDateTime>,
+    pub size: Option<i16>,
+    pub estimated_wait_time_time: Option<DatabaseDateTime>,
+    pub estimated_wait_time: Option<i16>,
+    pub wait_time_time: Option<DatabaseDateTime>,
+    pub wait_time: Option<f64>,
+}
+
+impl From<DbRouletteSizeContribution> for RouletteSizeContribution {
+    #[allow(clippy::cast_sign_loss)]
+    fn from(db: DbRouletteSizeContribution) -> Self {
+        Self {
+            datacenter_id: DatabaseU16::from(db.datacenter_id).0,
+            languages: u8::try_from(db.languages as u16).unwrap_or_default().into(),
+            roulette_id: (db.roulette_id as u16).try_into().unwrap_or_default(),
+            role: db.role.into(),
+            size_time: db.size_time,
+            size: db.size,
+            estimated_wait_time_time: db.est_time_time,
+            estimated_wait_time: db.est_time,
+            wait_time_time: db.wait_time_time,
+            wait_time: db.wait_time,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct UserExport {
+    pub user_id: Uuid,
+    pub exported_at: DatabaseDateTime,
+    pub install: Option<InstallInfo>,
+    pub connections: Vec<Connection>,
+    pub login_recaps: Vec<LoginRecap>,
+    pub duty_recaps: Vec<DutyRecap>,
+    pub queue_sizes: Vec<QueueSizeContribution>,
+    pub roulette_sizes: Vec<RouletteSizeContribution>,
+}
+
+#[derive(Debug)]
+pub struct UserErasure {
+    // Discord accounts that were linked to the install
+    pub connection_ids: Vec<u64>,
+    pub login_recap_count: u64,
+    pub duty_recap_count: u64,
+}
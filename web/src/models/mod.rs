@@ -4,10 +4,14 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 
+pub mod client_version;
 pub mod duty;
 pub mod duty_db;
+pub mod duty_stats;
 pub mod job_info;
 pub mod login;
+pub mod me;
+pub mod subscription;
 pub mod summary;
 pub mod travel;
 pub mod world_info;
@@ -31,8 +35,144 @@ pub struct WorldQueryFilter {
     pub region_id: Option<Vec<u16>>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct TimeRangeQuery {
+    pub start: Option<DatabaseDateTime>,
+    pub end: Option<DatabaseDateTime>,
+    // Bucket size in seconds
+    pub bucket: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TimeRange {
+    pub start: DatabaseDateTime,
+    pub end: DatabaseDateTime,
+    pub bucket: time::Duration,
+}
+
+impl TimeRangeQuery {
+    const DEFAULT_RANGE: time::Duration = time::Duration::days(1);
+    const DEFAULT_BUCKET: u32 = 60 * 60;
+    const MIN_BUCKET: u32 = 5 * 60;
+    const MAX_RANGE: time::Duration = time::Duration::days(90);
+    const MAX_BUCKETS: i64 = 2000;
+
+    pub fn resolve(self) -> Result<TimeRange, &'static str> {
+        let end = self
+            .end
+            .unwrap_or_else(|| time::OffsetDateTime::now_utc().into());
+        let start = self
+            .start
+            .unwrap_or_else(|| (end.0 - Self::DEFAULT_RANGE).into());
+        let bucket = self.bucket.unwrap_or(Self::DEFAULT_BUCKET);
+
+        if start >= end {
+            return Err("start must be before end");
+        }
+        if end.0 - start.0 > Self::MAX_RANGE {
+            return Err("Time range is too large");
+        }
+        if bucket < Self::MIN_BUCKET {
+            return Err("Bucket size is too small");
+        }
+        let bucket = time::Duration::seconds(bucket.into());
+        if (end.0 - start.0).whole_seconds() / bucket.whole_seconds() > Self::MAX_BUCKETS {
+            return Err("Too many buckets");
+        }
+
+        Ok(TimeRange { start, end, bucket })
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct RouletteQueryFilter {
     pub roulette_id: Option<Vec<u8>>,
     pub lang: QueueLanguage,
+    #[serde(default)]
+    pub aggregation: LanguageAggregation,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LanguageAggregation {
+    // Only estimates for the exact language combination
+    #[default]
+    Exact,
+    // Estimates for all language subsets and supersets, weighted by recency
+    Compatible,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ContentQueryFilter {
+    pub content_id: Option<Vec<u16>>,
+    pub lang: QueueLanguage,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PartyQueryFilter {
+    pub roulette_id: Option<u8>,
+    pub content_id: Option<u16>,
+    pub lang: QueueLanguage,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const END: time::OffsetDateTime = time::OffsetDateTime::UNIX_EPOCH;
+
+    fn query(
+        start: Option<time::OffsetDateTime>,
+        end: Option<time::OffsetDateTime>,
+        bucket: Option<u32>,
+    ) -> TimeRangeQuery {
+        TimeRangeQuery {
+            start: start.map(Into::into),
+            end: end.map(Into::into),
+            bucket,
+        }
+    }
+
+    #[test]
+    fn test_time_range_defaults() {
+        let range = query(None, Some(END), None).resolve().unwrap();
+        assert_eq!(range.end, END.into());
+        assert_eq!(range.start, (END - TimeRangeQuery::DEFAULT_RANGE).into());
+        assert_eq!(
+            range.bucket,
+            time::Duration::seconds(TimeRangeQuery::DEFAULT_BUCKET.into())
+        );
+    }
+
+    #[test]
+    fn test_time_range_explicit() {
+        let start = END - time::Duration::days(7);
+        let range = query(Some(start), Some(END), Some(3600 * 6))
+            .resolve()
+            .unwrap();
+        assert_eq!(range.start, start.into());
+        assert_eq!(range.bucket, time::Duration::hours(6));
+    }
+
+    #[test]
+    fn test_time_range_rejects_invalid() {
+        assert!(query(Some(END), Some(END), None).resolve().is_err());
+        assert!(
+            query(Some(END + time::Duration::hours(1)), Some(END), None)
+                .resolve()
+                .is_err()
+        );
+        assert!(
+            query(Some(END - time::Duration::days(91)), Some(END), None)
+                .resolve()
+                .is_err()
+        );
+        assert!(query(None, Some(END), Some(60)).resolve().is_err());
+        // 90 days of 5 minute buckets
+        assert!(
+            query(Some(END - time::Duration::days(90)), Some(END), Some(300))
+                .resolve()
+                .is_err()
+        );
+    }
 }
@@ -0,0 +1,61 @@
+use crate::storage::db::wrappers::DatabaseDateTime;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "subscription_endpoint", rename_all = "lowercase")]
+pub enum DbSubscriptionEndpoint {
+    Datacenter,
+    World,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "subscription_state", rename_all = "lowercase")]
+pub enum DbSubscriptionState {
+    // Waiting for the endpoint to open
+    Active,
+    // The endpoint opened and the reminder hasn't been delivered yet
+    Pending,
+    Delivered,
+    // Delivery failed permanently
+    Disabled,
+    // Expired before the endpoint opened
+    Expired,
+    // Unsubscribed while the reminder was being delivered
+    Cancelled,
+}
+
+#[derive(Debug, FromRow)]
+pub struct DbSubscription {
+    pub endpoint_type: DbSubscriptionEndpoint,
+    pub endpoint_id: i16,
+    pub state: DbSubscriptionState,
+    pub created_at: DatabaseDateTime,
+    pub persistent: bool,
+    pub cooldown_secs: i32,
+    pub expires_at: Option<DatabaseDateTime>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct DbClaimedSubscription {
+    pub id: Uuid,
+    pub endpoint_type: DbSubscriptionEndpoint,
+    pub endpoint_id: i16,
+    pub discord_user_id: i64,
+    pub attempts: i16,
+    pub persistent: bool,
+}
+
+#[derive(Debug)]
+pub enum DeliveryOutcome {
+    Delivered,
+    // Try again at the given time
+    Retry {
+        error: String,
+        retry_at: DatabaseDateTime,
+    },
+    // Never try again
+    Disabled {
+        reason: String,
+    },
+}
@@ -1,4 +1,7 @@
-use crate::storage::db::wrappers::DatabaseDateTime;
+use crate::{
+    models::{duty::RoleDemand, login::EstimateConfidence},
+    storage::db::wrappers::DatabaseDateTime,
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, sqlx::FromRow)]
@@ -20,6 +23,9 @@ pub struct DbWorldSummaryInfo {
     pub time: Option<time::PrimitiveDateTime>,
     pub size: Option<i32>,
     pub duration: Option<f64>,
+    pub duration_p50: Option<f64>,
+    pub duration_p90: Option<f64>,
+    pub sample_count: Option<i64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -41,6 +47,9 @@ pub struct WorldSummaryInfo {
     pub queue_time: DatabaseDateTime,
     pub queue_size: i32,
     pub queue_duration: f64,
+    pub queue_duration_p50: f64,
+    pub queue_duration_p90: f64,
+    pub queue_sample_count: i64,
 }
 
 impl From<DbWorldSummaryInfo> for WorldSummaryInfo {
@@ -63,6 +72,9 @@ impl From<DbWorldSummaryInfo> for WorldSummaryInfo {
             queue_time: DatabaseDateTime::from(db.time.unwrap_or(time::PrimitiveDateTime::MIN)),
             queue_size: db.size.unwrap_or_default(),
             queue_duration: db.duration.unwrap_or_default(),
+            queue_duration_p50: db.duration_p50.unwrap_or_default(),
+            queue_duration_p90: db.duration_p90.unwrap_or_default(),
+            queue_sample_count: db.sample_count.unwrap_or_default(),
         }
     }
 }
@@ -89,6 +101,10 @@ pub struct WorldSummary {
     pub queue_size: i32,
     pub queue_duration: f64,
     pub queue_last_update: DatabaseDateTime,
+    pub queue_duration_p50: f64,
+    pub queue_duration_p90: f64,
+    pub queue_sample_count: i64,
+    pub queue_confidence: EstimateConfidence,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -96,6 +112,7 @@ pub struct DatacenterSummary {
     pub id: u16,
     pub name: String,
     pub region_id: u16,
+    pub role_demand: Vec<RoleDemand>,
     // pub lobby_ping: u32,
     // pub server_ping: u32,
     // pub packet_loss: f32,
@@ -1,4 +1,5 @@
-use serde::Deserialize;
+use crate::storage::db::wrappers::DatabaseDateTime;
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug, sqlx::FromRow)]
 pub struct DbTravelState {
@@ -6,6 +7,150 @@ pub struct DbTravelState {
     pub prohibit: bool,
 }
 
+#[derive(Debug, sqlx::FromRow)]
+pub struct DbTravelStateChange {
+    pub world_id: i16,
+    pub time: DatabaseDateTime,
+    pub prohibit: bool,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct DbTravelClosure {
+    pub world_id: i16,
+    pub start: DatabaseDateTime,
+    // None if the world is still closed
+    pub end: Option<DatabaseDateTime>,
+    // The closure started before the queried history did, so its start is a lower bound
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TravelPrediction {
+    pub closed_since: DatabaseDateTime,
+    pub open_probabilities: Vec<OpenProbability>,
+    // Number of past closures the prediction is based on
+    pub sample_count: usize,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct OpenProbability {
+    pub minutes: u32,
+    pub probability: f64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TravelHistoryQuery {
+    pub start: Option<DatabaseDateTime>,
+    pub end: Option<DatabaseDateTime>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TravelHistoryRange {
+    pub start: DatabaseDateTime,
+    pub end: DatabaseDateTime,
+}
+
+impl TravelHistoryQuery {
+    const DEFAULT_RANGE: time::Duration = time::Duration::days(1);
+    const MAX_RANGE: time::Duration = time::Duration::days(90);
+
+    pub fn resolve(self) -> Result<TravelHistoryRange, &'static str> {
+        self.resolve_at(time::OffsetDateTime::now_utc().into())
+    }
+
+    // Nothing is known past now, so the range can't extend into the future
+    fn resolve_at(self, now: DatabaseDateTime) -> Result<TravelHistoryRange, &'static str> {
+        let end = self.end.map_or(now, |end| end.min(now));
+        let start = self
+            .start
+            .unwrap_or_else(|| (end.0 - Self::DEFAULT_RANGE).into());
+
+        if start >= end {
+            return Err("start must be before end");
+        }
+        if end.0 - start.0 > Self::MAX_RANGE {
+            return Err("Time range is too large");
+        }
+
+        Ok(TravelHistoryRange { start, end })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TravelHistory {
+    pub world_id: u16,
+    // Fraction of the known time range that the world was open for travel
+    pub open_rate: f64,
+    // In seconds
+    pub open_duration: f64,
+    pub closed_duration: f64,
+    pub intervals: Vec<TravelInterval>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TravelInterval {
+    pub start: DatabaseDateTime,
+    pub end: DatabaseDateTime,
+    pub is_open: bool,
+    // In seconds
+    pub duration: f64,
+}
+
+impl TravelHistory {
+    // States must be sorted by time. The first state may be from before the
+    // range, since it's needed to know what the world started out as.
+    pub fn new(world_id: u16, states: &[DbTravelStateChange], range: TravelHistoryRange) -> Self {
+        let mut intervals: Vec<TravelInterval> = Vec::new();
+        for (i, state) in states.iter().enumerate() {
+            let start = state.time.max(range.start);
+            let end = states
+                .get(i + 1)
+                .map_or(range.end, |s| s.time.min(range.end));
+            if start >= end {
+                continue;
+            }
+
+            let is_open = !state.prohibit;
+            // travel_states also records changes to the other flags
+            if let Some(last) = intervals.last_mut().filter(|l| l.is_open == is_open) {
+                last.end = end;
+                last.duration = (last.end.0 - last.start.0).as_seconds_f64();
+                continue;
+            }
+            intervals.push(TravelInterval {
+                start,
+                end,
+                is_open,
+                duration: (end.0 - start.0).as_seconds_f64(),
+            });
+        }
+
+        let open_duration = intervals
+            .iter()
+            .filter(|i| i.is_open)
+            .map(|i| i.duration)
+            .sum::<f64>();
+        let closed_duration = intervals
+            .iter()
+            .filter(|i| !i.is_open)
+            .map(|i| i.duration)
+            .sum::<f64>();
+        let known_duration = open_duration + closed_duration;
+
+        Self {
+            world_id,
+            open_rate: if known_duration > 0.0 {
+                open_duration / known_duration
+            } else {
+                0.0
+            },
+            open_duration,
+            closed_duration,
+            intervals,
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct DCTravelResponse {
     pub error: Option<String>,
@@ -55,3 +200,94 @@ pub struct DCTravelWorldInfo {
     #[serde(rename = "prohibitFlag")]
     pub prohibit: u8,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(minutes: i64) -> DatabaseDateTime {
+        (time::OffsetDateTime::UNIX_EPOCH + time::Duration::minutes(minutes)).into()
+    }
+
+    fn state(minutes: i64, prohibit: bool) -> DbTravelStateChange {
+        DbTravelStateChange {
+            world_id: 1,
+            time: at(minutes),
+            prohibit,
+        }
+    }
+
+    fn range(start: i64, end: i64) -> TravelHistoryRange {
+        TravelHistoryRange {
+            start: at(start),
+            end: at(end),
+        }
+    }
+
+    fn query(start: Option<i64>, end: Option<i64>) -> TravelHistoryQuery {
+        TravelHistoryQuery {
+            start: start.map(at),
+            end: end.map(at),
+        }
+    }
+
+    #[test]
+    fn test_travel_history_clamps_to_range() {
+        // The first state is from before the range
+        let states = [state(-30, true), state(30, false)];
+        let history = TravelHistory::new(1, &states, range(0, 120));
+
+        assert_eq!(history.intervals.len(), 2);
+        assert_eq!(history.intervals[0].start, at(0));
+        assert_eq!(history.intervals[0].end, at(30));
+        assert!(!history.intervals[0].is_open);
+        assert_eq!(history.intervals[1].start, at(30));
+        assert_eq!(history.intervals[1].end, at(120));
+        assert!(history.intervals[1].is_open);
+        assert_eq!(history.closed_duration, 30.0 * 60.0);
+        assert_eq!(history.open_duration, 90.0 * 60.0);
+        assert_eq!(history.open_rate, 0.75);
+    }
+
+    #[test]
+    fn test_travel_history_merges_repeated_states() {
+        let states = [state(0, false), state(20, false), state(40, true)];
+        let history = TravelHistory::new(1, &states, range(0, 60));
+
+        assert_eq!(history.intervals.len(), 2);
+        assert_eq!(history.intervals[0].end, at(40));
+        assert_eq!(history.intervals[0].duration, 40.0 * 60.0);
+    }
+
+    #[test]
+    fn test_travel_history_without_states() {
+        let history = TravelHistory::new(1, &[], range(0, 60));
+
+        assert!(history.intervals.is_empty());
+        assert_eq!(history.open_rate, 0.0);
+    }
+
+    #[test]
+    fn test_travel_history_query_allows_long_ranges() {
+        let now = at(90 * 24 * 60);
+        let range = query(Some(0), None).resolve_at(now).unwrap();
+
+        assert_eq!(range.start, at(0));
+        assert_eq!(range.end, now);
+    }
+
+    #[test]
+    fn test_travel_history_query_clamps_end_to_now() {
+        let range = query(Some(0), Some(120)).resolve_at(at(60)).unwrap();
+        assert_eq!(range.end, at(60));
+
+        assert!(query(Some(90), Some(120)).resolve_at(at(60)).is_err());
+    }
+
+    #[test]
+    fn test_travel_history_query_rejects_invalid() {
+        let now = at(100 * 24 * 60);
+        assert!(query(Some(60), Some(60)).resolve_at(now).is_err());
+        assert!(query(Some(0), None).resolve_at(now).is_err());
+    }
+}
@@ -0,0 +1,34 @@
+use crate::{
+    middleware::auth::AdminAuthentication,
+    models::client_version::ClientVersionStatsQuery,
+    storage::db::{self, wrappers::DatabaseDateTime},
+};
+use actix_web::{
+    dev::HttpServiceFactory, error::ErrorInternalServerError, route, web, HttpResponse, Result,
+};
+use sqlx::PgPool;
+
+const DEFAULT_DAYS: u32 = 30;
+// Matches the window of the client_version_stats view
+const MAX_DAYS: u32 = 90;
+
+pub fn service() -> impl HttpServiceFactory {
+    web::scope("/admin")
+        .wrap(AdminAuthentication)
+        .service(get_client_versions)
+}
+
+#[route("/client_versions/", method = "GET")]
+async fn get_client_versions(
+    pool: web::Data<PgPool>,
+    query: web::Query<ClientVersionStatsQuery>,
+) -> Result<HttpResponse> {
+    let days = query.days.unwrap_or(DEFAULT_DAYS).clamp(1, MAX_DAYS);
+    let since = time::OffsetDateTime::now_utc() - time::Duration::days(days.into());
+
+    let stats = db::client_versions::get_client_version_stats(&pool, DatabaseDateTime::from(since))
+        .await
+        .map_err(ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(stats))
+}
@@ -44,7 +44,18 @@ async fn delete_connection(
         return Err(ErrorNotFound("Connection not found"));
     }
 
-    if !db::connections::does_connection_id_exist(&pool, id)
+    offboard_connection(&pool, &discord, id).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// Call after the connection row is deleted
+pub(super) async fn offboard_connection(
+    pool: &PgPool,
+    discord: &DiscordClient,
+    id: u64,
+) -> Result<()> {
+    if !db::connections::does_connection_id_exist(pool, id)
         .await
         .map_err(ErrorInternalServerError)?
     {
@@ -61,7 +72,5 @@ async fn delete_connection(
     discord
         .offboard_user(UserId::new(id))
         .await
-        .map_err(ErrorInternalServerError)?;
-
-    Ok(HttpResponse::NoContent().finish())
+        .map_err(ErrorInternalServerError)
 }
@@ -0,0 +1,65 @@
+use crate::{
+    middleware::auth::{generate_secret, BasicAuthentication},
+    storage::db,
+};
+use actix_web::{
+    dev::HttpServiceFactory,
+    error::{ErrorConflict, ErrorInternalServerError, ErrorNotFound},
+    route, web, HttpResponse, Result,
+};
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub fn service() -> impl HttpServiceFactory {
+    web::scope("/installs")
+        .service(register_install)
+        .service(rotate_install_secret)
+}
+
+#[derive(Debug, Serialize)]
+struct InstallSecret {
+    secret: String,
+}
+
+#[derive(Debug, Serialize)]
+struct InstallRegistration {
+    username: Uuid,
+    secret: String,
+}
+
+// Issues a new username and secret to an install. The secret is used as the
+// basic auth password for all future requests. Usernames are always picked by
+// the server, since the legacy password is public and can't prove that an
+// existing username belongs to whoever is registering.
+#[route("/", method = "POST")]
+async fn register_install(pool: web::Data<PgPool>) -> Result<HttpResponse> {
+    let username = Uuid::now_v7();
+    let (secret, secret_hash) = generate_secret();
+    let created = db::installs::create_install(&pool, username, &secret_hash)
+        .await
+        .map_err(ErrorInternalServerError)?;
+
+    if !created {
+        return Err(ErrorConflict("Install is already registered"));
+    }
+
+    Ok(HttpResponse::Created().json(InstallRegistration { username, secret }))
+}
+
+#[route("/rotate/", method = "POST", wrap = "BasicAuthentication")]
+async fn rotate_install_secret(
+    pool: web::Data<PgPool>,
+    username: web::ReqData<Uuid>,
+) -> Result<HttpResponse> {
+    let (secret, secret_hash) = generate_secret();
+    let updated = db::installs::update_install_secret(&pool, *username, &secret_hash)
+        .await
+        .map_err(ErrorInternalServerError)?;
+
+    if !updated {
+        return Err(ErrorNotFound("Install is not registered"));
+    }
+
+    Ok(HttpResponse::Ok().json(InstallSecret { secret }))
+}
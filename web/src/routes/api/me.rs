@@ -0,0 +1,110 @@
+use super::connections::offboard_connection;
+use crate::{
+    discord::DiscordClient,
+    middleware::auth::BasicAuthentication,
+    models::me::{RecapHistoryQuery, UserStats},
+    storage::db,
+};
+use actix_web::{
+    dev::HttpServiceFactory,
+    error::{ErrorBadRequest, ErrorInternalServerError},
+    http::header::ContentDisposition,
+    route, web, HttpResponse, Result,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub fn service() -> impl HttpServiceFactory {
+    web::scope("/me")
+        .service(get_login_recaps)
+        .service(get_duty_recaps)
+        .service(get_stats)
+        .service(export_data)
+        .service(delete_data)
+}
+
+#[route("/recaps/login/", method = "GET", wrap = "BasicAuthentication")]
+async fn get_login_recaps(
+    pool: web::Data<PgPool>,
+    username: web::ReqData<Uuid>,
+    query: actix_web_lab::extract::Query<RecapHistoryQuery>,
+) -> Result<HttpResponse> {
+    let filter = query.into_inner().resolve().map_err(ErrorBadRequest)?;
+
+    let resp = db::me::get_login_recaps(&pool, *username, filter).await;
+    match resp {
+        Ok(page) => Ok(HttpResponse::Ok().json(page)),
+        Err(e) => Err(ErrorInternalServerError(e)),
+    }
+}
+
+#[route("/recaps/duty/", method = "GET", wrap = "BasicAuthentication")]
+async fn get_duty_recaps(
+    pool: web::Data<PgPool>,
+    username: web::ReqData<Uuid>,
+    query: actix_web_lab::extract::Query<RecapHistoryQuery>,
+) -> Result<HttpResponse> {
+    let filter = query.into_inner().resolve().map_err(ErrorBadRequest)?;
+
+    let resp = db::me::get_duty_recaps(&pool, *username, filter).await;
+    match resp {
+        Ok(page) => Ok(HttpResponse::Ok().json(page)),
+        Err(e) => Err(ErrorInternalServerError(e)),
+    }
+}
+
+#[route("/stats/", method = "GET", wrap = "BasicAuthentication")]
+async fn get_stats(pool: web::Data<PgPool>, username: web::ReqData<Uuid>) -> Result<HttpResponse> {
+    let login = db::me::get_login_stats(&pool, *username);
+    let duty = db::me::get_duty_stats(&pool, *username);
+    match tokio::join!(login, duty) {
+        (Ok(login), Ok(duty)) => Ok(HttpResponse::Ok().json(UserStats { login, duty })),
+        (Err(e), _) | (_, Err(e)) => Err(ErrorInternalServerError(e)),
+    }
+}
+
+#[route("/export/", method = "GET", wrap = "BasicAuthentication")]
+async fn export_data(
+    pool: web::Data<PgPool>,
+    username: web::ReqData<Uuid>,
+) -> Result<HttpResponse> {
+    let resp = db::me::get_user_export(&pool, *username).await;
+    match resp {
+        Ok(export) => Ok(HttpResponse::Ok()
+            .insert_header(ContentDisposition::attachment(format!(
+                "waitingway-{}.json",
+                *username
+            )))
+            .json(export)),
+        Err(e) => Err(ErrorInternalServerError(e)),
+    }
+}
+
+// Erases everything tied to the install, including the install itself
+#[route("/", method = "DELETE", wrap = "BasicAuthentication")]
+async fn delete_data(
+    pool: web::Data<PgPool>,
+    discord: web::Data<DiscordClient>,
+    username: web::ReqData<Uuid>,
+) -> Result<HttpResponse> {
+    let erasure = db::me::erase_user(&pool, *username)
+        .await
+        .map_err(ErrorInternalServerError)?;
+
+    log::info!(
+        "Erased user {}: {} login recaps, {} duty recaps, {} connections",
+        *username,
+        erasure.login_recap_count,
+        erasure.duty_recap_count,
+        erasure.connection_ids.len()
+    );
+
+    // The data is already gone, so a failed offboard shouldn't fail the request
+    for id in erasure.connection_ids {
+        if let Err(e) = offboard_connection(&pool, &discord, id).await {
+            log::warn!("Failed to offboard connection {}: {}", id, e);
+        }
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -1,5 +1,8 @@
+mod admin;
 mod base;
 mod connections;
+mod installs;
+mod me;
 mod notifications;
 mod oauth;
 mod queue;
@@ -22,10 +25,14 @@ fn v1() -> impl HttpServiceFactory {
         .service(summary::service())
         .service(oauth::service())
         .service(connections::service())
+        .service(installs::service())
+        .service(admin::service())
 }
 
 fn v2() -> impl HttpServiceFactory {
-    web::scope("/v2").service(queue::service())
+    web::scope("/v2")
+        .service(queue::service())
+        .service(me::service())
 }
 
 pub fn service() -> impl HttpServiceFactory {
@@ -1,10 +1,22 @@
 use crate::{
+    cache::{cached_response, CacheKey},
+    estimators,
+    idempotency::{idempotent_response, IdempotencyHeader, IdempotencyKey},
     middleware::{auth::BasicAuthentication, version::UserAgentVersion},
-    models::{duty::Recap, duty::RouletteSize, RouletteQueryFilter},
-    storage::db,
+    models::{
+        duty::Recap,
+        duty::RouletteSize,
+        duty_stats::{OutcomeQuery, WithdrawQuery},
+        ContentQueryFilter, LanguageAggregation, PartyQueryFilter, RouletteQueryFilter,
+        TimeRangeQuery,
+    },
+    storage::{db, redis::client::RedisClient},
+    validation::Validate,
 };
 use actix_web::{
-    dev::HttpServiceFactory, error::ErrorInternalServerError, get, route, web, HttpResponse, Result,
+    dev::HttpServiceFactory,
+    error::{ErrorBadRequest, ErrorInternalServerError},
+    get, route, web, HttpResponse, Result,
 };
 use sqlx::PgPool;
 use uuid::Uuid;
@@ -17,43 +29,76 @@ pub fn service() -> impl HttpServiceFactory {
         .service(create_recap)
         .service(get_roulette_estimate)
         .service(get_roulette_estimate_datacenter)
+        .service(get_roulette_history)
+        .service(get_roulette_outcomes)
+        .service(get_withdraw_history)
+        .service(get_content_estimate)
+        .service(get_content_estimate_datacenter)
+        .service(get_party_estimate_datacenter)
         .service(notifications::service())
 }
 
 #[route("/roulette/size/", method = "POST", wrap = "BasicAuthentication")]
 async fn create_roulette_size(
     pool: web::Data<PgPool>,
+    redis: web::Data<RedisClient>,
     username: web::ReqData<Uuid>,
+    idempotency: IdempotencyHeader,
     size_info: web::Json<RouletteSize>,
 ) -> Result<HttpResponse> {
     let mut size_info = size_info.into_inner();
     size_info.user_id = *username;
+    let key = IdempotencyKey::new(
+        *username,
+        "duty_roulette_size",
+        idempotency,
+        size_info.submission_id.take(),
+    )?;
 
-    let resp = db::duty::create_roulette_size(&pool, size_info).await;
-    match resp {
-        Ok(_) => Ok(HttpResponse::Ok().finish()),
-        Err(e) => Err(ErrorInternalServerError(e)),
-    }
+    idempotent_response((**redis).clone(), key, || async move {
+        let resp = db::duty::create_roulette_size(&pool, size_info).await;
+        match resp {
+            Ok(_) => Ok(HttpResponse::Ok().finish()),
+            Err(e) => Err(ErrorInternalServerError(e)),
+        }
+    })
+    .await
 }
 
 #[route("/recap/", method = "POST", wrap = "BasicAuthentication")]
 async fn create_recap(
     pool: web::Data<PgPool>,
+    redis: web::Data<RedisClient>,
     username: web::ReqData<Uuid>,
     ua_version: UserAgentVersion,
+    idempotency: IdempotencyHeader,
     recap: web::Json<Recap>,
 ) -> Result<HttpResponse> {
     let mut recap = recap.into_inner();
     recap.client_version = ua_version;
     recap.user_id = *username;
     recap.id = Uuid::now_v7();
+    let key = IdempotencyKey::new(
+        *username,
+        "duty_recap",
+        idempotency,
+        recap.submission_id.take(),
+    )?;
 
-    let resp = db::duty::create_recap(&pool, recap).await;
-
-    match resp {
-        Ok(_) => Ok(HttpResponse::Created().finish()),
-        Err(e) => Err(ErrorInternalServerError(e)),
+    let quarantine_reason = recap.validate()?;
+    if let Some(reason) = quarantine_reason {
+        log::warn!("Quarantining recap {}: {}", recap.id, reason);
     }
+
+    idempotent_response((**redis).clone(), key, || async move {
+        let resp = db::duty::create_recap(&pool, recap, quarantine_reason).await;
+
+        match resp {
+            Ok(_) => Ok(HttpResponse::Created().finish()),
+            Err(e) => Err(ErrorInternalServerError(e)),
+        }
+    })
+    .await
 }
 
 #[get("/roulette/")]
@@ -72,7 +117,15 @@ async fn get_roulette_estimate_datacenter(
     filter: actix_web_lab::extract::Query<RouletteQueryFilter>,
 ) -> Result<HttpResponse> {
     let filter = filter.into_inner();
-    let resp = if let Some(roulette_id) = filter.roulette_id {
+    let resp = if filter.aggregation == LanguageAggregation::Compatible {
+        estimators::roulette::estimate_compatible(
+            &pool,
+            *datacenter_id,
+            filter.lang,
+            filter.roulette_id,
+        )
+        .await
+    } else if let Some(roulette_id) = filter.roulette_id {
         db::duty::get_roulette_estimates_by_datacenter_id_filtered(
             &pool,
             *datacenter_id,
@@ -89,3 +142,135 @@ async fn get_roulette_estimate_datacenter(
         Err(e) => Err(ErrorInternalServerError(e)),
     }
 }
+
+#[get("/roulette/{datacenter_id}/history/")]
+async fn get_roulette_history(
+    pool: web::Data<PgPool>,
+    datacenter_id: web::Path<u16>,
+    filter: actix_web_lab::extract::Query<RouletteQueryFilter>,
+    range: actix_web_lab::extract::Query<TimeRangeQuery>,
+) -> Result<HttpResponse> {
+    let filter = filter.into_inner();
+    let range = range.into_inner().resolve().map_err(ErrorBadRequest)?;
+
+    let resp = db::duty::get_roulette_history(
+        &pool,
+        *datacenter_id,
+        filter.lang,
+        filter.roulette_id,
+        range,
+    )
+    .await;
+
+    match resp {
+        Ok(history) => Ok(HttpResponse::Ok().json(history)),
+        Err(e) => Err(ErrorInternalServerError(e)),
+    }
+}
+
+#[get("/roulette/{datacenter_id}/outcomes/")]
+async fn get_roulette_outcomes(
+    pool: web::Data<PgPool>,
+    cache: web::Data<RedisClient>,
+    datacenter_id: web::Path<u16>,
+    query: actix_web_lab::extract::Query<OutcomeQuery>,
+) -> Result<HttpResponse> {
+    let query = query.into_inner();
+    let days = query.days.unwrap_or(OutcomeQuery::DEFAULT_DAYS);
+    if days == 0 || days > OutcomeQuery::MAX_DAYS {
+        return Err(ErrorBadRequest("Invalid number of days"));
+    }
+    let roulette_ids = query.roulette_id.map(|mut ids| {
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    });
+    let key = CacheKey::RouletteOutcomes {
+        datacenter_id: *datacenter_id,
+        roulette_ids: roulette_ids.clone(),
+        days,
+    };
+
+    cached_response((**cache).clone(), key, || async move {
+        let since = time::OffsetDateTime::now_utc() - time::Duration::days(days.into());
+        db::duty_stats::get_roulette_outcomes(&pool, *datacenter_id, roulette_ids, since.into())
+            .await
+            .map_err(ErrorInternalServerError)
+    })
+    .await
+}
+
+#[get("/withdrawals/{datacenter_id}/")]
+async fn get_withdraw_history(
+    pool: web::Data<PgPool>,
+    datacenter_id: web::Path<u16>,
+    query: actix_web_lab::extract::Query<WithdrawQuery>,
+    range: actix_web_lab::extract::Query<TimeRangeQuery>,
+) -> Result<HttpResponse> {
+    let range = range.into_inner().resolve().map_err(ErrorBadRequest)?;
+
+    let resp = db::duty_stats::get_withdraw_history(
+        &pool,
+        *datacenter_id,
+        query.into_inner().roulette_id,
+        range,
+    )
+    .await;
+
+    match resp {
+        Ok(history) => Ok(HttpResponse::Ok().json(history)),
+        Err(e) => Err(ErrorInternalServerError(e)),
+    }
+}
+
+#[get("/content/")]
+async fn get_content_estimate(pool: web::Data<PgPool>) -> Result<HttpResponse> {
+    let resp = db::duty::get_content_estimates(&pool).await;
+    match resp {
+        Ok(estimate) => Ok(HttpResponse::Ok().json(estimate)),
+        Err(e) => Err(ErrorInternalServerError(e)),
+    }
+}
+
+#[get("/content/{datacenter_id}/")]
+async fn get_content_estimate_datacenter(
+    pool: web::Data<PgPool>,
+    datacenter_id: web::Path<u16>,
+    filter: actix_web_lab::extract::Query<ContentQueryFilter>,
+) -> Result<HttpResponse> {
+    let filter = filter.into_inner();
+    let resp = db::duty::get_content_estimates_by_datacenter_id(
+        &pool,
+        *datacenter_id,
+        filter.lang,
+        filter.content_id,
+    )
+    .await;
+
+    match resp {
+        Ok(estimate) => Ok(HttpResponse::Ok().json(estimate)),
+        Err(e) => Err(ErrorInternalServerError(e)),
+    }
+}
+
+#[get("/party/{datacenter_id}/")]
+async fn get_party_estimate_datacenter(
+    pool: web::Data<PgPool>,
+    datacenter_id: web::Path<u16>,
+    filter: web::Query<PartyQueryFilter>,
+) -> Result<HttpResponse> {
+    let filter = filter.into_inner();
+    let resp = db::duty::get_party_estimates_by_datacenter_id(
+        &pool,
+        *datacenter_id,
+        filter.lang,
+        filter.roulette_id,
+        filter.content_id,
+    )
+    .await;
+
+    match resp {
+        Ok(estimate) => Ok(HttpResponse::Ok().json(estimate)),
+        Err(e) => Err(ErrorInternalServerError(e)),
+    }
+}
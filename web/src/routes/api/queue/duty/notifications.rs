@@ -56,6 +56,8 @@ struct DeleteData {
     pub resulting_content: Option<u16>,
     pub error_message: Option<String>,
     pub error_code: Option<u16>,
+    #[serde(default)]
+    pub withdraw_message: Option<u16>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -173,6 +175,7 @@ impl NotificationInstance for InstanceData {
             data.resulting_content,
             data.error_message.clone(),
             data.error_code,
+            data.withdraw_message,
         )
         .await
     }
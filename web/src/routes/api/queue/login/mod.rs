@@ -1,11 +1,21 @@
 use crate::{
+    estimators,
+    idempotency::{IdempotencyHeader, IdempotencyKey, idempotent_response},
     middleware::{auth::BasicAuthentication, version::UserAgentVersion},
-    models::{WorldQueryFilter, login::QueueSize, login::Recap},
-    storage::db,
+    models::{
+        TimeRangeQuery, WorldQueryFilter,
+        login::{HeatmapQuery, QueueSize, Recap},
+    },
+    storage::{db, game::worlds, redis::client::RedisClient},
+    validation::Validate,
 };
 use actix_web::{
-    HttpResponse, Result, dev::HttpServiceFactory, error::ErrorInternalServerError, get, route, web,
+    HttpResponse, Result,
+    dev::HttpServiceFactory,
+    error::{ErrorBadRequest, ErrorInternalServerError, ErrorNotFound},
+    get, route, web,
 };
+use serde::Deserialize;
 use sqlx::PgPool;
 use uuid::Uuid;
 
@@ -16,43 +26,74 @@ pub fn service() -> impl HttpServiceFactory {
         .service(create_size)
         .service(create_recap)
         .service(get_queue_estimate)
+        .service(get_queue_history)
+        .service(get_wait_estimate)
+        .service(get_error_counts)
+        .service(get_queue_heatmap)
         .service(notifications::service())
 }
 
 #[route("/size/", method = "POST", wrap = "BasicAuthentication")]
 async fn create_size(
     pool: web::Data<PgPool>,
+    redis: web::Data<RedisClient>,
     username: web::ReqData<Uuid>,
+    idempotency: IdempotencyHeader,
     size_info: web::Json<QueueSize>,
 ) -> Result<HttpResponse> {
     let mut size_info = size_info.into_inner();
     size_info.user_id = *username;
+    let key = IdempotencyKey::new(
+        *username,
+        "login_size",
+        idempotency,
+        size_info.submission_id.take(),
+    )?;
 
-    let resp = db::login::create_queue_size(&pool, size_info).await;
-    match resp {
-        Ok(_) => Ok(HttpResponse::Ok().finish()),
-        Err(e) => Err(ErrorInternalServerError(e)),
-    }
+    idempotent_response((**redis).clone(), key, || async move {
+        let resp = db::login::create_queue_size(&pool, size_info).await;
+        match resp {
+            Ok(_) => Ok(HttpResponse::Ok().finish()),
+            Err(e) => Err(ErrorInternalServerError(e)),
+        }
+    })
+    .await
 }
 
 #[route("/recap/", method = "POST", wrap = "BasicAuthentication")]
 async fn create_recap(
     pool: web::Data<PgPool>,
+    redis: web::Data<RedisClient>,
     username: web::ReqData<Uuid>,
     ua_version: UserAgentVersion,
+    idempotency: IdempotencyHeader,
     recap: web::Json<Recap>,
 ) -> Result<HttpResponse> {
     let mut recap = recap.into_inner();
     recap.client_version = ua_version;
     recap.user_id = *username;
     recap.id = Uuid::now_v7();
+    let key = IdempotencyKey::new(
+        *username,
+        "login_recap",
+        idempotency,
+        recap.submission_id.take(),
+    )?;
 
-    let resp = db::login::create_recap(&pool, recap).await;
-
-    match resp {
-        Ok(_) => Ok(HttpResponse::Created().finish()),
-        Err(e) => Err(ErrorInternalServerError(e)),
+    let quarantine_reason = recap.validate()?;
+    if let Some(reason) = quarantine_reason {
+        log::warn!("Quarantining recap {}: {}", recap.id, reason);
     }
+
+    idempotent_response((**redis).clone(), key, || async move {
+        let resp = db::login::create_recap(&pool, recap, quarantine_reason).await;
+
+        match resp {
+            Ok(_) => Ok(HttpResponse::Created().finish()),
+            Err(e) => Err(ErrorInternalServerError(e)),
+        }
+    })
+    .await
 }
 
 #[get("/")]
@@ -76,3 +117,98 @@ async fn get_queue_estimate(
         Err(e) => Err(ErrorInternalServerError(e)),
     }
 }
+
+#[get("/history/")]
+async fn get_queue_history(
+    pool: web::Data<PgPool>,
+    filter: actix_web_lab::extract::Query<WorldQueryFilter>,
+    range: actix_web_lab::extract::Query<TimeRangeQuery>,
+) -> Result<HttpResponse> {
+    let range = range.into_inner().resolve().map_err(ErrorBadRequest)?;
+    let world_ids = worlds::get_data()
+        .filter_worlds(&filter.into_inner())
+        .into_iter()
+        .map(|w| w.id)
+        .collect();
+
+    let resp = db::login::get_queue_history(&pool, world_ids, range).await;
+
+    match resp {
+        Ok(history) => Ok(HttpResponse::Ok().json(history)),
+        Err(e) => Err(ErrorInternalServerError(e)),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct WaitEstimateQuery {
+    position: u32,
+}
+
+#[get("/estimate/{world_id}/")]
+async fn get_wait_estimate(
+    pool: web::Data<PgPool>,
+    world_id: web::Path<u16>,
+    query: web::Query<WaitEstimateQuery>,
+) -> Result<HttpResponse> {
+    let resp = estimators::login::estimate_wait(&pool, *world_id, query.position)
+        .await
+        .map_err(ErrorInternalServerError)?;
+
+    match resp {
+        Some(estimate) => Ok(HttpResponse::Ok().json(estimate)),
+        None => Err(ErrorNotFound("Not enough recent data to estimate")),
+    }
+}
+
+#[get("/errors/")]
+async fn get_error_counts(
+    pool: web::Data<PgPool>,
+    filter: actix_web_lab::extract::Query<WorldQueryFilter>,
+    range: actix_web_lab::extract::Query<TimeRangeQuery>,
+) -> Result<HttpResponse> {
+    let range = range.into_inner().resolve().map_err(ErrorBadRequest)?;
+    let world_ids = worlds::get_data()
+        .filter_worlds(&filter.into_inner())
+        .into_iter()
+        .map(|w| w.id)
+        .collect();
+
+    let resp = db::login::get_error_counts(&pool, world_ids, range).await;
+
+    match resp {
+        Ok(counts) => Ok(HttpResponse::Ok().json(counts)),
+        Err(e) => Err(ErrorInternalServerError(e)),
+    }
+}
+
+#[get("/heatmap/")]
+async fn get_queue_heatmap(
+    pool: web::Data<PgPool>,
+    query: web::Query<HeatmapQuery>,
+) -> Result<HttpResponse> {
+    let query = query.into_inner();
+    let timezone = query
+        .tz
+        .unwrap_or_else(|| HeatmapQuery::DEFAULT_TIMEZONE.to_string());
+    let days = query.days.unwrap_or(HeatmapQuery::DEFAULT_DAYS);
+    if days == 0 || days > HeatmapQuery::MAX_DAYS {
+        return Err(ErrorBadRequest("Invalid number of days"));
+    }
+    if worlds::get_data().get_world_by_id(query.world_id).is_none() {
+        return Err(ErrorNotFound("Unknown world"));
+    }
+    if !db::login::is_valid_timezone(&pool, &timezone)
+        .await
+        .map_err(ErrorInternalServerError)?
+    {
+        return Err(ErrorBadRequest("Unknown time zone"));
+    }
+
+    let since = time::OffsetDateTime::now_utc() - time::Duration::days(days.into());
+    let resp = db::login::get_queue_heatmap(&pool, query.world_id, timezone, since.into()).await;
+
+    match resp {
+        Ok(heatmap) => Ok(HttpResponse::Ok().json(heatmap)),
+        Err(e) => Err(ErrorInternalServerError(e)),
+    }
+}
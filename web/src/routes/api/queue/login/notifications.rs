@@ -96,6 +96,7 @@ impl NotificationInstance for InstanceData {
             discord,
             id,
             &data.character_name,
+            data.world_id,
             data.update_data.position,
             data.update_data.updated_at,
             data.update_data.estimated_time,
@@ -115,6 +116,7 @@ impl NotificationInstance for InstanceData {
             message,
             channel,
             &self.character_name,
+            self.world_id,
             data.position,
             data.updated_at,
             data.estimated_time,
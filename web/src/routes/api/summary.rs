@@ -1,11 +1,16 @@
 use crate::{
     cache::{cached_response, CacheKey},
-    models::summary::{DatacenterSummary, RegionSummary, Summary, WorldSummary, WorldSummaryInfo},
+    models::{
+        duty::RoleDemand,
+        login::EstimateConfidence,
+        summary::{DatacenterSummary, RegionSummary, Summary, WorldSummary, WorldSummaryInfo},
+    },
     storage::{db, redis::client::RedisClient},
 };
 use actix_web::{
     dev::HttpServiceFactory, error::ErrorInternalServerError, get, web, HttpResponse, Result,
 };
+use itertools::Itertools;
 use sqlx::PgPool;
 use std::collections::HashMap;
 
@@ -21,17 +26,27 @@ async fn get_summary(
     cached_response((**cache).clone(), CacheKey::WorldSummary, || async {
         let world_summaries = db::summary::get_world_summaries(&pool);
         let travel_time = db::travel::get_travel_time(&pool);
-        match tokio::join!(world_summaries, travel_time) {
-            (Ok(world_summaries), Ok(travel_time)) => {
-                Ok(construct_summary(&world_summaries, travel_time))
-            }
-            (Err(e), _) | (_, Err(e)) => Err(ErrorInternalServerError(e)),
+        let role_demand = db::duty::get_role_demand(&pool);
+        match tokio::join!(world_summaries, travel_time, role_demand) {
+            (Ok(world_summaries), Ok(travel_time), Ok(role_demand)) => Ok(construct_summary(
+                &world_summaries,
+                travel_time,
+                role_demand,
+            )),
+            (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => Err(ErrorInternalServerError(e)),
         }
     })
     .await
 }
 
-fn construct_summary(world_summaries: &[WorldSummaryInfo], travel_time: i32) -> Summary {
+fn construct_summary(
+    world_summaries: &[WorldSummaryInfo],
+    travel_time: i32,
+    role_demand: Vec<RoleDemand>,
+) -> Summary {
+    let mut role_demand = role_demand
+        .into_iter()
+        .into_group_map_by(|demand| demand.datacenter_id);
     let mut regions = HashMap::new();
     let mut datacenters = HashMap::new();
     let mut worlds = HashMap::new();
@@ -50,6 +65,7 @@ fn construct_summary(world_summaries: &[WorldSummaryInfo], travel_time: i32) ->
                 id: world.datacenter_id,
                 name: world.datacenter_name.clone(),
                 region_id: world.region_id,
+                role_demand: role_demand.remove(&world.datacenter_id).unwrap_or_default(),
             });
 
         worlds
@@ -67,6 +83,10 @@ fn construct_summary(world_summaries: &[WorldSummaryInfo], travel_time: i32) ->
                 queue_size: world.queue_size,
                 queue_duration: world.queue_duration,
                 queue_last_update: world.queue_time,
+                queue_duration_p50: world.queue_duration_p50,
+                queue_duration_p90: world.queue_duration_p90,
+                queue_sample_count: world.queue_sample_count,
+                queue_confidence: EstimateConfidence::from_sample_count(world.queue_sample_count),
             });
     }
     Summary {
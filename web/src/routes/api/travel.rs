@@ -1,19 +1,30 @@
-use crate::{models::WorldQueryFilter, storage::db};
+use crate::{
+    estimators,
+    models::{
+        travel::{TravelHistoryQuery, TravelPrediction},
+        WorldQueryFilter,
+    },
+    storage::db,
+};
 use actix_web::{
-    dev::HttpServiceFactory, error::ErrorInternalServerError, get, web, HttpResponse, Result,
+    dev::HttpServiceFactory,
+    error::{ErrorBadRequest, ErrorInternalServerError},
+    get, web, HttpResponse, Result,
 };
 use serde::Serialize;
 use sqlx::PgPool;
 use std::collections::HashMap;
 
 pub fn service() -> impl HttpServiceFactory {
-    get_travel_state
+    (get_travel_state, get_travel_history)
 }
 
 #[derive(Debug, Serialize)]
 pub struct TravelStates {
     pub travel_time: i32,
     pub prohibited: HashMap<u16, bool>,
+    // Only for prohibited worlds with enough history
+    pub predictions: HashMap<u16, TravelPrediction>,
 }
 
 #[get("/travel/")]
@@ -24,12 +35,40 @@ async fn get_travel_state(
     let filter = filter.into_inner();
     let resp = get_travel_state_filtered(&pool, filter);
     let time = db::travel::get_travel_time(&pool);
-    match tokio::join!(resp, time) {
-        (Ok(states), Ok(time)) => Ok(HttpResponse::Ok().json(TravelStates {
-            travel_time: time,
-            prohibited: states,
-        })),
-        (Err(e), _) | (_, Err(e)) => Err(ErrorInternalServerError(e)),
+    let (states, time) = match tokio::join!(resp, time) {
+        (Ok(states), Ok(time)) => (states, time),
+        (Err(e), _) | (_, Err(e)) => return Err(ErrorInternalServerError(e)),
+    };
+
+    let closed_world_ids = states
+        .iter()
+        .filter(|(_, prohibited)| **prohibited)
+        .map(|(world_id, _)| *world_id)
+        .collect::<Vec<_>>();
+    let predictions = estimators::travel::predict_openings(&pool, closed_world_ids)
+        .await
+        .map_err(ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(TravelStates {
+        travel_time: time,
+        prohibited: states,
+        predictions,
+    }))
+}
+
+// Open/closed intervals per world
+#[get("/travel/history/")]
+async fn get_travel_history(
+    pool: web::Data<PgPool>,
+    filter: actix_web_lab::extract::Query<WorldQueryFilter>,
+    range: actix_web_lab::extract::Query<TravelHistoryQuery>,
+) -> Result<HttpResponse> {
+    let range = range.into_inner().resolve().map_err(ErrorBadRequest)?;
+
+    let resp = db::travel::get_travel_history(&pool, filter.into_inner(), range).await;
+    match resp {
+        Ok(history) => Ok(HttpResponse::Ok().json(history)),
+        Err(e) => Err(ErrorInternalServerError(e)),
     }
 }
 
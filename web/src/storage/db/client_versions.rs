@@ -0,0 +1,23 @@
+use super::wrappers::DatabaseDateTime;
+use crate::models::client_version::{ClientVersionStat, DbClientVersionStat};
+use sqlx::{postgres::PgQueryResult, Error, PgPool};
+
+pub async fn refresh_client_version_stats(pool: &PgPool) -> Result<PgQueryResult, Error> {
+    sqlx::query!(r#"REFRESH MATERIALIZED VIEW CONCURRENTLY client_version_stats"#)
+        .execute(pool)
+        .await
+}
+
+pub async fn get_client_version_stats(
+    pool: &PgPool,
+    since: DatabaseDateTime,
+) -> Result<Vec<ClientVersionStat>, Error> {
+    sqlx::query_as!(
+        DbClientVersionStat,
+        r#"SELECT * FROM client_version_stats WHERE day >= $1"#,
+        since.as_db()
+    )
+    .fetch_all(pool)
+    .await
+    .map(|stats| stats.into_iter().map(ClientVersionStat::from).collect())
+}
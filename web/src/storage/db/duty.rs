@@ -2,18 +2,27 @@ use super::wrappers::{DatabaseDateTime, DatabaseU16};
 use crate::{
     models::{
         duty::{
-            PartyMember, QueueLanguage, Recap, RecapUpdateData, RouletteEstimate, RoulettePosition,
-            RouletteSize, WaitTime,
+            ContentEstimate, PartyEstimate, PartyMakeup, PartyMember, PartyRoles, QueueLanguage,
+            Recap, RecapUpdateData, RoleDemand, RouletteEstimate, RouletteHistory,
+            RouletteHistoryBucket, RoulettePosition, RouletteSize, WaitTime,
         },
-        duty_db::{DbRecapUpdateType, DbRouletteEstimate, DbRouletteRole},
+        duty_db::{
+            DbContentEstimate, DbPartyEstimate, DbRecapUpdateType, DbRoleDemand,
+            DbRouletteEstimate, DbRouletteHistoryBucket, DbRouletteRole,
+        },
+        TimeRange,
     },
     storage::game::{jobs, worlds},
 };
 use itertools::Itertools;
-use sqlx::{Error, PgPool, QueryBuilder};
+use sqlx::{postgres::PgQueryResult, Error, PgPool, QueryBuilder};
 use std::io;
 
-pub async fn create_recap(pool: &PgPool, recap: Recap) -> Result<(), Error> {
+pub async fn create_recap(
+    pool: &PgPool,
+    recap: Recap,
+    quarantine_reason: Option<&str>,
+) -> Result<(), Error> {
     // Limit the number of updates to 1000
     if recap.updates.len() > 1000 {
         return Err(Error::Decode(Box::new(io::Error::new(
@@ -55,8 +64,8 @@ pub async fn create_recap(pool: &PgPool, recap: Recap) -> Result<(), Error> {
 
     let mut tx = pool.begin().await?;
 
-    // Solo roulette queues only
-    if recap.party.is_none() {
+    // Solo roulette queues only, and never from quarantined recaps
+    if recap.party.is_none() && quarantine_reason.is_none() {
         if let Some(roulette) = recap.queued_roulette {
             let Some(role) = queued_job.role else {
                 return Err(Error::Decode(Box::new(io::Error::new(
@@ -179,6 +188,7 @@ pub async fn create_recap(pool: &PgPool, recap: Recap) -> Result<(), Error> {
         }
     }
 
+    let composition = recap.party.as_ref().and_then(PartyMakeup::composition);
     let members = recap.party.as_ref().map(|p| &p.members);
     let queued_content = recap
         .queued_content
@@ -212,6 +222,35 @@ pub async fn create_recap(pool: &PgPool, recap: Recap) -> Result<(), Error> {
     .execute(&mut *tx)
     .await?;
 
+    if let Some(reason) = quarantine_reason {
+        sqlx::query!(
+            r#"--sql;
+            INSERT INTO duty_recap_quarantine
+            (recap_id, time, reason)
+            VALUES ($1, NOW() AT TIME ZONE 'UTC', $2)"#r,
+            recap.id,
+            reason
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    if let Some(composition) = composition {
+        sqlx::query!(
+            r#"--sql;
+            INSERT INTO duty_party_compositions
+            (recap_id, member_count, tanks, healers, dps)
+            VALUES ($1, $2, $3, $4, $5)"#r,
+            recap.id,
+            DatabaseU16(composition.member_count.into()).as_db(),
+            DatabaseU16(composition.tanks.into()).as_db(),
+            DatabaseU16(composition.healers.into()).as_db(),
+            DatabaseU16(composition.dps.into()).as_db()
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
     if !recap.updates.is_empty() {
         let mut query_builder = QueryBuilder::new(
             "INSERT INTO duty_updates (recap_id, time, reserving, update_type, wait_time, position, fill_params) ",
@@ -357,6 +396,7 @@ pub async fn get_roulette_estimates(pool: &PgPool) -> Result<Vec<RouletteEstimat
             datacenter_id, languages, roulette_id,
             role AS "role: DbRouletteRole",
             GREATEST(size_time, est_time_time, wait_time_time) as "time: DatabaseDateTime",
+            wait_time_time as "duration_time: DatabaseDateTime",
             wait_time as duration, est_time as wait_time, size
         FROM roulette_sizes"#
     )
@@ -377,6 +417,7 @@ pub async fn get_roulette_estimates_by_datacenter_id(
             datacenter_id, languages, roulette_id,
             role AS "role: DbRouletteRole",
             GREATEST(size_time, est_time_time, wait_time_time) as "time: DatabaseDateTime",
+            wait_time_time as "duration_time: DatabaseDateTime",
             wait_time as duration, est_time as wait_time, size
         FROM roulette_sizes
         WHERE datacenter_id = $1 AND languages = $2"#,
@@ -405,6 +446,7 @@ pub async fn get_roulette_estimates_by_datacenter_id_filtered(
             datacenter_id, languages, roulette_id,
             role AS "role: DbRouletteRole",
             GREATEST(size_time, est_time_time, wait_time_time) as "time: DatabaseDateTime",
+            wait_time_time as "duration_time: DatabaseDateTime",
             wait_time as duration, est_time as wait_time, size
         FROM roulette_sizes
         WHERE datacenter_id = $1 AND languages = $2 AND roulette_id = ANY($3)"#,
@@ -416,3 +458,270 @@ pub async fn get_roulette_estimates_by_datacenter_id_filtered(
     .await
     .map(|estimates| estimates.into_iter().map(RouletteEstimate::from).collect())
 }
+
+pub async fn get_roulette_estimates_by_datacenter_id_compatible(
+    pool: &PgPool,
+    datacenter_id: u16,
+    languages: QueueLanguage,
+    roulette_ids: Option<Vec<u8>>,
+) -> Result<Vec<RouletteEstimate>, Error> {
+    let roulette_ids = roulette_ids.map(|ids| {
+        ids.into_iter()
+            .map(|id| DatabaseU16(id.into()).as_db())
+            .collect::<Vec<_>>()
+    });
+    sqlx::query_as!(
+        DbRouletteEstimate,
+        r#"--sql;
+        SELECT
+            datacenter_id, languages, roulette_id,
+            role AS "role: DbRouletteRole",
+            GREATEST(size_time, est_time_time, wait_time_time) as "time: DatabaseDateTime",
+            wait_time_time as "duration_time: DatabaseDateTime",
+            wait_time as duration, est_time as wait_time, size
+        FROM roulette_sizes
+        WHERE datacenter_id = $1
+        AND (languages & $2) IN (languages, $2)
+        AND ($3::smallint[] IS NULL OR roulette_id = ANY($3))"#,
+        DatabaseU16(datacenter_id).as_db(),
+        DatabaseU16(u8::from(languages).into()).as_db(),
+        roulette_ids.as_deref()
+    )
+    .fetch_all(pool)
+    .await
+    .map(|estimates| estimates.into_iter().map(RouletteEstimate::from).collect())
+}
+
+// Built from the recaps themselves rather than roulette_sizes, since that only
+// keeps the latest value for each roulette
+pub async fn get_roulette_history(
+    pool: &PgPool,
+    datacenter_id: u16,
+    languages: QueueLanguage,
+    roulette_ids: Option<Vec<u8>>,
+    range: TimeRange,
+) -> Result<Vec<RouletteHistory>, Error> {
+    let roulette_ids = roulette_ids.map(|ids| {
+        ids.into_iter()
+            .map(|id| DatabaseU16(id.into()).as_db())
+            .collect::<Vec<_>>()
+    });
+    let buckets = sqlx::query_as!(
+        DbRouletteHistoryBucket,
+        r#"--sql;
+        SELECT
+            r.queued_roulette AS "roulette_id!",
+            j.role AS "role!: DbRouletteRole",
+            date_bin(make_interval(secs => $5), r.start_time, TIMESTAMP '2000-01-01') AS "time!: DatabaseDateTime",
+            percentile_cont(0.5) WITHIN GROUP (
+                ORDER BY EXTRACT(EPOCH FROM (p.time - r.start_time))::double precision
+            ) AS duration,
+            percentile_cont(0.5) WITHIN GROUP (
+                ORDER BY u.wait_time * 60.0
+            ) AS estimated_duration,
+            COUNT(*) AS "count!"
+        FROM duty_recaps r
+        JOIN worlds w ON w.world_id = r.world_id
+        JOIN jobs j ON j.id = r.queued_job
+        CROSS JOIN LATERAL (
+            SELECT MIN(time) AS time
+            FROM duty_pops p
+            WHERE p.recap_id = r.id
+        ) p
+        LEFT JOIN LATERAL (
+            SELECT u.wait_time
+            FROM duty_updates u
+            WHERE u.recap_id = r.id
+            AND u.update_type = 'roulette'
+            AND u.wait_time BETWEEN 1 AND 254
+            AND u.time < p.time
+            ORDER BY u.time DESC
+            LIMIT 1
+        ) u ON TRUE
+        WHERE w.datacenter_id = $1
+        AND ((r.queued_flags >> 9) & 15)::smallint = $2
+        AND ($3::smallint[] IS NULL OR r.queued_roulette = ANY($3))
+        AND r.queued_roulette IS NOT NULL
+        AND r.party_members IS NULL
+        AND j.role IS NOT NULL
+        AND p.time IS NOT NULL
+        AND r.start_time >= $4
+        AND r.start_time < $6
+        AND NOT EXISTS (SELECT 1 FROM duty_recap_quarantine q WHERE q.recap_id = r.id)
+        GROUP BY 1, 2, 3
+        ORDER BY 1, 2, 3"#,
+        DatabaseU16(datacenter_id).as_db(),
+        DatabaseU16(u8::from(languages).into()).as_db(),
+        roulette_ids.as_deref(),
+        range.start.as_db(),
+        range.bucket.as_seconds_f64(),
+        range.end.as_db()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(buckets
+        .into_iter()
+        .chunk_by(|b| (b.roulette_id, b.role as u8))
+        .into_iter()
+        .filter_map(|(_, buckets)| {
+            let buckets = buckets.collect_vec();
+            let first = buckets.first()?;
+            let roulette_id = u8::try_from(DatabaseU16::from(first.roulette_id).0).ok()?;
+            let role = first.role.into();
+            Some(RouletteHistory::new(
+                roulette_id,
+                role,
+                buckets
+                    .into_iter()
+                    .map(RouletteHistoryBucket::from)
+                    .collect(),
+            ))
+        })
+        .collect())
+}
+
+pub async fn refresh_content_estimates(pool: &PgPool) -> Result<PgQueryResult, Error> {
+    sqlx::query!(r#"REFRESH MATERIALIZED VIEW CONCURRENTLY content_estimates"#)
+        .execute(pool)
+        .await
+}
+
+pub async fn get_content_estimates(pool: &PgPool) -> Result<Vec<ContentEstimate>, Error> {
+    sqlx::query_as!(
+        DbContentEstimate,
+        r#"--sql;
+        SELECT
+            datacenter_id, languages, content_id,
+            role AS "role!: DbRouletteRole",
+            time AS "time: DatabaseDateTime",
+            duration_p50, duration_p90, sample_count
+        FROM content_estimates"#
+    )
+    .fetch_all(pool)
+    .await
+    .map(|estimates| estimates.into_iter().map(ContentEstimate::from).collect())
+}
+
+pub async fn get_content_estimates_by_datacenter_id(
+    pool: &PgPool,
+    datacenter_id: u16,
+    languages: QueueLanguage,
+    content_ids: Option<Vec<u16>>,
+) -> Result<Vec<ContentEstimate>, Error> {
+    let content_ids = content_ids.map(|ids| {
+        ids.into_iter()
+            .map(|id| DatabaseU16(id).as_db())
+            .collect::<Vec<_>>()
+    });
+    sqlx::query_as!(
+        DbContentEstimate,
+        r#"--sql;
+        SELECT
+            datacenter_id, languages, content_id,
+            role AS "role!: DbRouletteRole",
+            time AS "time: DatabaseDateTime",
+            duration_p50, duration_p90, sample_count
+        FROM content_estimates
+        WHERE datacenter_id = $1 AND languages = $2
+        AND ($3::smallint[] IS NULL OR content_id = ANY($3))"#,
+        DatabaseU16(datacenter_id).as_db(),
+        DatabaseU16(u8::from(languages).into()).as_db(),
+        content_ids.as_deref()
+    )
+    .fetch_all(pool)
+    .await
+    .map(|estimates| estimates.into_iter().map(ContentEstimate::from).collect())
+}
+
+pub async fn refresh_party_estimates(pool: &PgPool) -> Result<PgQueryResult, Error> {
+    sqlx::query!(r#"REFRESH MATERIALIZED VIEW CONCURRENTLY party_estimates"#)
+        .execute(pool)
+        .await
+}
+
+pub async fn upsert_party_roles(
+    pool: &PgPool,
+    roles: Vec<(u8, u16, PartyRoles)>,
+) -> Result<(), Error> {
+    if roles.is_empty() {
+        return Ok(());
+    }
+    let mut query_builder = QueryBuilder::new(
+        r#"--sql;
+            INSERT INTO content_party_roles (roulette_id, content_id, tanks, healers, dps)
+            "#,
+    );
+    query_builder.push_values(roles, |mut b, (roulette_id, content_id, roles)| {
+        b.push_bind(DatabaseU16(roulette_id.into()).as_db())
+            .push_bind(DatabaseU16(content_id).as_db())
+            .push_bind(DatabaseU16(roles.tanks.into()).as_db())
+            .push_bind(DatabaseU16(roles.healers.into()).as_db())
+            .push_bind(DatabaseU16(roles.dps.into()).as_db());
+    });
+    query_builder.push(
+        r#"
+        ON CONFLICT (roulette_id, content_id) DO UPDATE
+            SET tanks = EXCLUDED.tanks,
+                healers = EXCLUDED.healers,
+                dps = EXCLUDED.dps
+            "#,
+    );
+    query_builder.build().execute(pool).await?;
+
+    Ok(())
+}
+
+pub async fn get_party_estimates_by_datacenter_id(
+    pool: &PgPool,
+    datacenter_id: u16,
+    languages: QueueLanguage,
+    roulette_id: Option<u8>,
+    content_id: Option<u16>,
+) -> Result<Vec<PartyEstimate>, Error> {
+    sqlx::query_as!(
+        DbPartyEstimate,
+        r#"--sql;
+        SELECT
+            datacenter_id, languages, roulette_id, content_id,
+            tanks_needed, healers_needed, dps_needed,
+            time AS "time: DatabaseDateTime",
+            duration_p50, duration_p90, sample_count
+        FROM party_estimates
+        WHERE datacenter_id = $1 AND languages = $2
+        AND ($3::smallint IS NULL OR roulette_id = $3)
+        AND ($4::smallint IS NULL OR content_id = $4)"#,
+        DatabaseU16(datacenter_id).as_db(),
+        DatabaseU16(u8::from(languages).into()).as_db(),
+        roulette_id.map(|id| DatabaseU16(id.into()).as_db()),
+        content_id.map(|id| DatabaseU16(id).as_db())
+    )
+    .fetch_all(pool)
+    .await
+    .map(|estimates| estimates.into_iter().map(PartyEstimate::from).collect())
+}
+
+pub async fn refresh_role_demand(pool: &PgPool) -> Result<PgQueryResult, Error> {
+    sqlx::query!(r#"REFRESH MATERIALIZED VIEW CONCURRENTLY role_demand"#)
+        .execute(pool)
+        .await
+}
+
+pub async fn get_role_demand(pool: &PgPool) -> Result<Vec<RoleDemand>, Error> {
+    sqlx::query_as!(
+        DbRoleDemand,
+        r#"--sql;
+        SELECT
+            datacenter_id AS "datacenter_id!",
+            role AS "role!: DbRouletteRole",
+            relative_wait,
+            wait_sample_count,
+            fill_rate,
+            fill_sample_count
+        FROM role_demand
+        ORDER BY 1, 2"#
+    )
+    .fetch_all(pool)
+    .await
+    .map(|demand| demand.into_iter().map(RoleDemand::from).collect())
+}
@@ -0,0 +1,127 @@
+use super::wrappers::{DatabaseDateTime, DatabaseU16};
+use crate::models::{
+    duty_stats::{
+        DbPopOutcome, DbWithdrawCount, RouletteOutcomes, WithdrawBucket, WithdrawHistory,
+        WithdrawReason,
+    },
+    TimeRange,
+};
+use itertools::Itertools;
+use sqlx::{Error, PgPool};
+
+// Every pop counts, including ones that were declined or fell through, since
+// each is still a draw from the roulette's pool of duties
+pub async fn get_roulette_outcomes(
+    pool: &PgPool,
+    datacenter_id: u16,
+    roulette_ids: Option<Vec<u8>>,
+    since: DatabaseDateTime,
+) -> Result<Vec<RouletteOutcomes>, Error> {
+    let roulette_ids = roulette_ids.map(|ids| {
+        ids.into_iter()
+            .map(|id| DatabaseU16(id.into()).as_db())
+            .collect::<Vec<_>>()
+    });
+    let outcomes = sqlx::query_as!(
+        DbPopOutcome,
+        r#"--sql;
+        SELECT
+            r.queued_roulette AS "roulette_id!",
+            p.content AS content_id,
+            COUNT(*) AS "pop_count!",
+            COUNT(*) FILTER (WHERE p.in_progress_time IS NOT NULL) AS "in_progress_count!"
+        FROM duty_pops p
+        JOIN duty_recaps r ON r.id = p.recap_id
+        JOIN worlds w ON w.world_id = r.world_id
+        WHERE w.datacenter_id = $1
+        AND r.queued_roulette IS NOT NULL
+        AND ($2::smallint[] IS NULL OR r.queued_roulette = ANY($2))
+        AND r.start_time >= $3
+        AND NOT EXISTS (SELECT 1 FROM duty_recap_quarantine q WHERE q.recap_id = r.id)
+        GROUP BY 1, 2
+        ORDER BY 1, 2"#,
+        DatabaseU16(datacenter_id).as_db(),
+        roulette_ids.as_deref(),
+        since.as_db()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(outcomes
+        .into_iter()
+        .chunk_by(|o| o.roulette_id)
+        .into_iter()
+        .filter_map(|(roulette_id, outcomes)| {
+            let roulette_id = u8::try_from(DatabaseU16::from(roulette_id).0).ok()?;
+            Some(RouletteOutcomes::new(roulette_id, outcomes.collect()))
+        })
+        .collect())
+}
+
+pub async fn get_withdraw_history(
+    pool: &PgPool,
+    datacenter_id: u16,
+    roulette_ids: Option<Vec<u8>>,
+    range: TimeRange,
+) -> Result<Vec<WithdrawHistory>, Error> {
+    let roulette_ids = roulette_ids.map(|ids| {
+        ids.into_iter()
+            .map(|id| DatabaseU16(id.into()).as_db())
+            .collect::<Vec<_>>()
+    });
+    let counts = sqlx::query_as!(
+        DbWithdrawCount,
+        r#"--sql;
+        SELECT
+            r.queued_roulette AS roulette_id,
+            date_bin(make_interval(secs => $5), r.start_time, TIMESTAMP '2000-01-01') AS "time!: DatabaseDateTime",
+            r.withdraw_message AS "withdraw_message!",
+            COUNT(*) AS "count!"
+        FROM duty_recaps r
+        JOIN worlds w ON w.world_id = r.world_id
+        WHERE w.datacenter_id = $1
+        AND r.withdraw_message IS NOT NULL
+        AND ($2::smallint[] IS NULL OR r.queued_roulette = ANY($2))
+        AND r.start_time >= $3
+        AND r.start_time < $4
+        GROUP BY 1, 2, 3
+        ORDER BY 1, 2, 4 DESC"#,
+        DatabaseU16(datacenter_id).as_db(),
+        roulette_ids.as_deref(),
+        range.start.as_db(),
+        range.end.as_db(),
+        range.bucket.as_seconds_f64()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(counts
+        .into_iter()
+        .chunk_by(|c| c.roulette_id)
+        .into_iter()
+        .map(|(roulette_id, counts)| WithdrawHistory {
+            roulette_id: roulette_id.and_then(|id| u8::try_from(id).ok()),
+            buckets: counts
+                .chunk_by(|c| c.time)
+                .into_iter()
+                .map(|(time, counts)| WithdrawBucket {
+                    time,
+                    reasons: counts.map(WithdrawReason::from).collect(),
+                })
+                .collect(),
+        })
+        .collect())
+}
+
+pub async fn get_withdraw_message_ids(pool: &PgPool) -> Result<Vec<u16>, Error> {
+    let ids = sqlx::query_scalar!(
+        r#"--sql;
+        SELECT DISTINCT withdraw_message AS "withdraw_message!"
+        FROM duty_recaps
+        WHERE withdraw_message IS NOT NULL"#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(ids.into_iter().map(|id| DatabaseU16::from(id).0).collect())
+}
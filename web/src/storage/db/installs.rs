@@ -0,0 +1,52 @@
+use sqlx::{Error, PgPool};
+use uuid::Uuid;
+
+pub async fn get_install_secret_hash(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<Option<Vec<u8>>, Error> {
+    sqlx::query_scalar!(
+        r#"SELECT secret_hash FROM installs WHERE user_id = $1"#,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+// Returns false if the install is already registered
+pub async fn create_install(
+    pool: &PgPool,
+    user_id: Uuid,
+    secret_hash: &[u8],
+) -> Result<bool, Error> {
+    sqlx::query!(
+        r#"--sql
+        INSERT INTO installs
+        (user_id, secret_hash, created_at, rotated_at)
+        VALUES ($1, $2, NOW() AT TIME ZONE 'UTC', NOW() AT TIME ZONE 'UTC')
+        ON CONFLICT (user_id) DO NOTHING"#r,
+        user_id,
+        secret_hash
+    )
+    .execute(pool)
+    .await
+    .map(|r| r.rows_affected() != 0)
+}
+
+pub async fn update_install_secret(
+    pool: &PgPool,
+    user_id: Uuid,
+    secret_hash: &[u8],
+) -> Result<bool, Error> {
+    sqlx::query!(
+        r#"--sql
+        UPDATE installs
+        SET secret_hash = $2, rotated_at = NOW() AT TIME ZONE 'UTC'
+        WHERE user_id = $1"#r,
+        user_id,
+        secret_hash
+    )
+    .execute(pool)
+    .await
+    .map(|r| r.rows_affected() != 0)
+}
@@ -1,9 +1,22 @@
-use super::wrappers::DatabaseU16;
-use crate::models::login::{DbQueueEstimate, QueueEstimate, QueueSize, Recap};
+use super::wrappers::{DatabaseDateTime, DatabaseU16};
+use crate::models::{
+    TimeRange,
+    login::{
+        DatacenterErrorRate, DbErrorCount, DbQueueEstimate, DbQueueHistoryBucket,
+        DbQueueHeatmapCell, DbQueueThroughput, ErrorCodeWindow, ErrorCount, QueueEstimate,
+        QueueHeatmap, QueueHeatmapCell, QueueHistory, QueueHistoryBucket, QueueSize,
+        QueueThroughput, Recap,
+    },
+};
+use itertools::Itertools;
 use sqlx::{postgres::PgQueryResult, Error, PgPool, QueryBuilder};
 use std::io;
 
-pub async fn create_recap(pool: &PgPool, recap: Recap) -> Result<(), Error> {
+pub async fn create_recap(
+    pool: &PgPool,
+    recap: Recap,
+    quarantine_reason: Option<&str>,
+) -> Result<(), Error> {
     // Limit the number of positions to half a week
     if recap.positions.len() > 60 * 24 * 7 {
         return Err(Error::Decode(Box::new(io::Error::new(
@@ -26,7 +39,8 @@ pub async fn create_recap(pool: &PgPool, recap: Recap) -> Result<(), Error> {
 
     let queue_size = recap.positions.last().map_or(0, |p| p.position);
 
-    if queue_size == 0 {
+    // Quarantined recaps shouldn't affect the current queue size either
+    if queue_size == 0 && quarantine_reason.is_none() {
         let queue_size_time = recap
             .positions
             .last()
@@ -74,6 +88,18 @@ pub async fn create_recap(pool: &PgPool, recap: Recap) -> Result<(), Error> {
     .execute(&mut *tx)
     .await?;
 
+    if let Some(reason) = quarantine_reason {
+        sqlx::query!(
+            r#"INSERT INTO recap_quarantine
+            (recap_id, time, reason)
+            VALUES ($1, NOW() AT TIME ZONE 'UTC', $2)"#r,
+            recap.id,
+            reason
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
     if !recap.positions.is_empty() {
         let mut query_builder = QueryBuilder::new(
             "INSERT INTO recap_positions (recap_id, time, identify_time, position) ",
@@ -116,6 +142,26 @@ pub async fn refresh_queue_estimates(pool: &PgPool) -> Result<PgQueryResult, Err
         .await
 }
 
+pub async fn refresh_queue_throughputs(pool: &PgPool) -> Result<PgQueryResult, Error> {
+    sqlx::query!(r#"REFRESH MATERIALIZED VIEW CONCURRENTLY queue_throughputs"#)
+        .execute(pool)
+        .await
+}
+
+pub async fn get_queue_throughput(
+    pool: &PgPool,
+    world_id: u16,
+) -> Result<Option<QueueThroughput>, Error> {
+    sqlx::query_as!(
+        DbQueueThroughput,
+        r#"SELECT * FROM queue_throughputs WHERE world_id = $1"#,
+        DatabaseU16(world_id).as_db()
+    )
+    .fetch_optional(pool)
+    .await
+    .map(|throughput| throughput.map(QueueThroughput::from))
+}
+
 pub async fn get_queue_estimates(pool: &PgPool) -> Result<Vec<QueueEstimate>, Error> {
     sqlx::query_as!(DbQueueEstimate, r#"SELECT * FROM queue_estimates"#)
         .fetch_all(pool)
@@ -186,3 +232,224 @@ pub async fn get_queue_estimates_by_world_id(
     .await
     .map(|estimates| estimates.into_iter().map(QueueEstimate::from).collect())
 }
+
+pub async fn get_queue_history(
+    pool: &PgPool,
+    world_ids: Vec<u16>,
+    range: TimeRange,
+) -> Result<Vec<QueueHistory>, Error> {
+    let world_ids = world_ids
+        .into_iter()
+        .map(|id| DatabaseU16(id).as_db())
+        .collect::<Vec<_>>();
+    let buckets = sqlx::query_as!(
+        DbQueueHistoryBucket,
+        r#"--sql;
+        SELECT
+            r.world_id AS "world_id!",
+            date_bin(make_interval(secs => $3), r.start_time, TIMESTAMP '2000-01-01') AS "time!: DatabaseDateTime",
+            percentile_cont(0.5) WITHIN GROUP (
+                ORDER BY EXTRACT(EPOCH FROM (r.end_time - p.time))::double precision
+            ) AS duration,
+            MAX(p.max_position) AS max_position,
+            COUNT(*) AS "count!"
+        FROM recaps r
+        CROSS JOIN LATERAL (
+            SELECT MIN(time) AS time, MAX(position) AS max_position
+            FROM recap_positions p
+            WHERE p.recap_id = r.id
+        ) p
+        WHERE r.world_id = ANY($4)
+        AND r.successful
+        AND NOT r.reentered
+        AND r.start_time >= $1
+        AND r.start_time < $2
+        GROUP BY 1, 2
+        ORDER BY 1, 2"#,
+        range.start.as_db(),
+        range.end.as_db(),
+        range.bucket.as_seconds_f64(),
+        world_ids.as_slice()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(buckets
+        .into_iter()
+        .chunk_by(|b| b.world_id)
+        .into_iter()
+        .map(|(world_id, buckets)| QueueHistory {
+            world_id: DatabaseU16::from(world_id).0,
+            buckets: buckets.map(QueueHistoryBucket::from).collect(),
+        })
+        .collect())
+}
+
+pub async fn get_error_counts(
+    pool: &PgPool,
+    world_ids: Vec<u16>,
+    range: TimeRange,
+) -> Result<Vec<ErrorCount>, Error> {
+    let world_ids = world_ids
+        .into_iter()
+        .map(|id| DatabaseU16(id).as_db())
+        .collect::<Vec<_>>();
+    sqlx::query_as!(
+        DbErrorCount,
+        r#"--sql;
+        SELECT
+            world_id AS "world_id!",
+            date_bin(make_interval(secs => $3), start_time, TIMESTAMP '2000-01-01') AS "time!: DatabaseDateTime",
+            error_code AS "error_code!",
+            COUNT(*) AS "count!"
+        FROM recaps
+        WHERE world_id = ANY($4)
+        AND error_code IS NOT NULL
+        AND start_time >= $1
+        AND start_time < $2
+        GROUP BY 1, 2, 3
+        ORDER BY 1, 2, 3"#,
+        range.start.as_db(),
+        range.end.as_db(),
+        range.bucket.as_seconds_f64(),
+        world_ids.as_slice()
+    )
+    .fetch_all(pool)
+    .await
+    .map(|counts| counts.into_iter().map(ErrorCount::from).collect())
+}
+
+pub async fn get_datacenter_error_rates(
+    pool: &PgPool,
+    since: DatabaseDateTime,
+) -> Result<Vec<DatacenterErrorRate>, Error> {
+    let rows = sqlx::query!(
+        r#"--sql;
+        SELECT
+            w.datacenter_id AS "datacenter_id!",
+            r.error_code,
+            COUNT(*) AS "count!"
+        FROM recaps r
+        JOIN worlds w ON r.world_id = w.world_id
+        WHERE r.start_time >= $1
+        AND NOT w.hidden
+        GROUP BY 1, 2
+        ORDER BY 1, 3 DESC"#,
+        since.as_db()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .chunk_by(|r| r.datacenter_id)
+        .into_iter()
+        .map(|(datacenter_id, rows)| {
+            let mut rate = DatacenterErrorRate {
+                datacenter_id: DatabaseU16::from(datacenter_id).0,
+                total: 0,
+                errors: 0,
+                codes: vec![],
+            };
+            for row in rows {
+                rate.total += row.count;
+                if let Some(code) = row.error_code {
+                    rate.errors += row.count;
+                    rate.codes.push((code, row.count));
+                }
+            }
+            rate
+        })
+        .collect())
+}
+
+pub async fn get_error_code_windows(
+    pool: &PgPool,
+    baseline_start: DatabaseDateTime,
+    recent_start: DatabaseDateTime,
+) -> Result<Vec<ErrorCodeWindow>, Error> {
+    let rows = sqlx::query!(
+        r#"--sql;
+        SELECT
+            w.datacenter_id AS "datacenter_id!",
+            r.error_code AS "error_code!",
+            COUNT(*) FILTER (WHERE r.start_time >= $2) AS "recent_count!",
+            COUNT(*) FILTER (WHERE r.start_time < $2) AS "baseline_count!"
+        FROM recaps r
+        JOIN worlds w ON r.world_id = w.world_id
+        WHERE r.error_code IS NOT NULL
+        AND r.start_time >= $1
+        GROUP BY 1, 2"#,
+        baseline_start.as_db(),
+        recent_start.as_db()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| ErrorCodeWindow {
+            datacenter_id: DatabaseU16::from(r.datacenter_id).0,
+            error_code: r.error_code,
+            recent_count: r.recent_count,
+            baseline_count: r.baseline_count,
+        })
+        .collect())
+}
+
+pub async fn is_valid_timezone(pool: &PgPool, timezone: &str) -> Result<bool, Error> {
+    sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM pg_timezone_names WHERE name = $1) AS "exists!""#,
+        timezone
+    )
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn get_queue_heatmap(
+    pool: &PgPool,
+    world_id: u16,
+    timezone: String,
+    since: DatabaseDateTime,
+) -> Result<QueueHeatmap, Error> {
+    let cells = sqlx::query_as!(
+        DbQueueHeatmapCell,
+        r#"--sql;
+        SELECT
+            (EXTRACT(ISODOW FROM l.time)::integer - 1) AS "day!",
+            EXTRACT(HOUR FROM l.time)::integer AS "hour!",
+            percentile_cont(0.5) WITHIN GROUP (
+                ORDER BY EXTRACT(EPOCH FROM (r.end_time - p.time))::double precision
+            ) AS duration,
+            percentile_cont(0.5) WITHIN GROUP (ORDER BY p.position) AS size,
+            COUNT(*) AS "count!"
+        FROM recaps r
+        CROSS JOIN LATERAL (
+            SELECT time, position
+            FROM recap_positions p
+            WHERE p.recap_id = r.id
+            ORDER BY p.time ASC
+            LIMIT 1
+        ) p
+        CROSS JOIN LATERAL (
+            SELECT (r.start_time AT TIME ZONE 'UTC') AT TIME ZONE $2 AS time
+        ) l
+        WHERE r.world_id = $1
+        AND r.successful
+        AND NOT r.reentered
+        AND r.start_time >= $3
+        GROUP BY 1, 2
+        ORDER BY 1, 2"#,
+        DatabaseU16(world_id).as_db(),
+        timezone,
+        since.as_db()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(QueueHeatmap {
+        world_id,
+        timezone,
+        cells: cells.into_iter().map(QueueHeatmapCell::from).collect(),
+    })
+}
@@ -0,0 +1,379 @@
+use super::{
+    connections::get_connections_by_user_id,
+    wrappers::{DatabaseDateTime, DatabaseU16, DatabaseU64},
+};
+use crate::models::{
+    duty_db::DbRouletteRole,
+    me::{
+        DbDutyRecap, DbDutyRecapPop, DbLoginRecap, DbLoginRecapPosition, DbQueueSizeContribution,
+        DbRecapStats, DbRouletteSizeContribution, DutyRecap, InstallInfo, LoginRecap,
+        QueueSizeContribution, RecapFilter, RecapPage, RecapStats, RouletteSizeContribution,
+        UserErasure, UserExport,
+    },
+};
+use itertools::Itertools;
+use sqlx::{Error, PgPool};
+use uuid::Uuid;
+
+fn to_db_world_ids(world_ids: Option<&[u16]>) -> Option<Vec<i16>> {
+    world_ids.map(|ids| ids.iter().map(|&id| DatabaseU16(id).as_db()).collect())
+}
+
+pub async fn get_login_recaps(
+    pool: &PgPool,
+    user_id: Uuid,
+    filter: RecapFilter,
+) -> Result<RecapPage<LoginRecap>, Error> {
+    let world_ids = to_db_world_ids(filter.world_ids.as_deref());
+    let start = filter.start.map(DatabaseDateTime::as_db);
+    let end = filter.end.map(DatabaseDateTime::as_db);
+
+    let total = sqlx::query_scalar!(
+        r#"--sql
+        SELECT COUNT(*) AS "count!"
+        FROM recaps
+        WHERE user_id = $1
+        AND ($2::smallint[] IS NULL OR world_id = ANY($2))
+        AND ($3::timestamp IS NULL OR start_time >= $3)
+        AND ($4::timestamp IS NULL OR start_time < $4)"#,
+        user_id,
+        world_ids.as_deref(),
+        start,
+        end
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let recaps = sqlx::query_as!(
+        DbLoginRecap,
+        r#"--sql
+        SELECT
+            id, world_id, free_trial, successful, reentered,
+            error_type, error_code, error_info, error_row,
+            start_time AS "start_time: DatabaseDateTime",
+            end_time AS "end_time: DatabaseDateTime",
+            end_identify_time AS "end_identify_time: DatabaseDateTime"
+        FROM recaps
+        WHERE user_id = $1
+        AND ($2::smallint[] IS NULL OR world_id = ANY($2))
+        AND ($3::timestamp IS NULL OR start_time >= $3)
+        AND ($4::timestamp IS NULL OR start_time < $4)
+        ORDER BY start_time DESC, id DESC
+        LIMIT $5 OFFSET $6"#,
+        user_id,
+        world_ids.as_deref(),
+        start,
+        end,
+        filter.limit.map(i64::from),
+        i64::from(filter.offset)
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let recap_ids = recaps.iter().map(|r| r.id).collect_vec();
+    let mut positions = sqlx::query_as!(
+        DbLoginRecapPosition,
+        r#"--sql
+        SELECT
+            recap_id,
+            time AS "time: DatabaseDateTime",
+            identify_time AS "identify_time: DatabaseDateTime",
+            position
+        FROM recap_positions
+        WHERE recap_id = ANY($1)
+        ORDER BY recap_id, time"#,
+        recap_ids.as_slice()
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .into_group_map_by(|p| p.recap_id);
+
+    Ok(RecapPage {
+        recaps: recaps
+            .into_iter()
+            .map(|r| {
+                let positions = positions
+                    .remove(&r.id)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map_into()
+                    .collect();
+                LoginRecap::new(r, positions)
+            })
+            .collect(),
+        total,
+    })
+}
+
+pub async fn get_duty_recaps(
+    pool: &PgPool,
+    user_id: Uuid,
+    filter: RecapFilter,
+) -> Result<RecapPage<DutyRecap>, Error> {
+    let world_ids = to_db_world_ids(filter.world_ids.as_deref());
+    let start = filter.start.map(DatabaseDateTime::as_db);
+    let end = filter.end.map(DatabaseDateTime::as_db);
+
+    let total = sqlx::query_scalar!(
+        r#"--sql
+        SELECT COUNT(*) AS "count!"
+        FROM duty_recaps
+        WHERE user_id = $1
+        AND ($2::smallint[] IS NULL OR world_id = ANY($2))
+        AND ($3::timestamp IS NULL OR start_time >= $3)
+        AND ($4::timestamp IS NULL OR start_time < $4)"#,
+        user_id,
+        world_ids.as_deref(),
+        start,
+        end
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let recaps = sqlx::query_as!(
+        DbDutyRecap,
+        r#"--sql
+        SELECT
+            id, queued_roulette, queued_content, queued_job, queued_flags,
+            world_id, is_party_leader,
+            start_time AS "start_time: DatabaseDateTime",
+            end_time AS "end_time: DatabaseDateTime",
+            withdraw_message
+        FROM duty_recaps
+        WHERE user_id = $1
+        AND ($2::smallint[] IS NULL OR world_id = ANY($2))
+        AND ($3::timestamp IS NULL OR start_time >= $3)
+        AND ($4::timestamp IS NULL OR start_time < $4)
+        ORDER BY start_time DESC, id DESC
+        LIMIT $5 OFFSET $6"#,
+        user_id,
+        world_ids.as_deref(),
+        start,
+        end,
+        filter.limit.map(i64::from),
+        i64::from(filter.offset)
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let recap_ids = recaps.iter().map(|r| r.id).collect_vec();
+    let mut pops = sqlx::query_as!(
+        DbDutyRecapPop,
+        r#"--sql
+        SELECT
+            recap_id,
+            time AS "time: DatabaseDateTime",
+            flags,
+            content,
+            in_progress_time AS "in_progress_time: DatabaseDateTime"
+        FROM duty_pops
+        WHERE recap_id = ANY($1)
+        ORDER BY recap_id, time"#,
+        recap_ids.as_slice()
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .into_group_map_by(|p| p.recap_id);
+
+    Ok(RecapPage {
+        recaps: recaps
+            .into_iter()
+            .map(|r| {
+                let pops = pops
+                    .remove(&r.id)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map_into()
+                    .collect();
+                DutyRecap::new(r, pops)
+            })
+            .collect(),
+        total,
+    })
+}
+
+pub async fn get_login_stats(pool: &PgPool, user_id: Uuid) -> Result<RecapStats, Error> {
+    sqlx::query_as!(
+        DbRecapStats,
+        r#"--sql
+        WITH durations AS (
+            SELECT
+                id, successful, start_time,
+                EXTRACT(EPOCH FROM (end_time - start_time))::double precision AS duration
+            FROM recaps
+            WHERE user_id = $1
+        )
+        SELECT
+            COUNT(*) AS "recap_count!",
+            COUNT(*) FILTER (WHERE successful) AS "successful_count!",
+            COALESCE(SUM(duration), 0) AS "total_queue_time!",
+            MAX(duration) AS longest_queue_time,
+            (array_agg(id ORDER BY duration DESC))[1] AS longest_queue_id,
+            MIN(start_time) AS "first_queue: DatabaseDateTime",
+            MAX(start_time) AS "last_queue: DatabaseDateTime"
+        FROM durations"#,
+        user_id
+    )
+    .fetch_one(pool)
+    .await
+    .map(RecapStats::from)
+}
+
+pub async fn get_duty_stats(pool: &PgPool, user_id: Uuid) -> Result<RecapStats, Error> {
+    sqlx::query_as!(
+        DbRecapStats,
+        r#"--sql
+        WITH durations AS (
+            SELECT
+                r.id, r.start_time,
+                r.withdraw_message IS NULL AND EXISTS (
+                    SELECT 1 FROM duty_pops p WHERE p.recap_id = r.id
+                ) AS successful,
+                EXTRACT(EPOCH FROM (r.end_time - r.start_time))::double precision AS duration
+            FROM duty_recaps r
+            WHERE r.user_id = $1
+        )
+        SELECT
+            COUNT(*) AS "recap_count!",
+            COUNT(*) FILTER (WHERE successful) AS "successful_count!",
+            COALESCE(SUM(duration), 0) AS "total_queue_time!",
+            MAX(duration) AS longest_queue_time,
+            (array_agg(id ORDER BY duration DESC))[1] AS longest_queue_id,
+            MIN(start_time) AS "first_queue: DatabaseDateTime",
+            MAX(start_time) AS "last_queue: DatabaseDateTime"
+        FROM durations"#,
+        user_id
+    )
+    .fetch_one(pool)
+    .await
+    .map(RecapStats::from)
+}
+
+pub async fn get_user_export(pool: &PgPool, user_id: Uuid) -> Result<UserExport, Error> {
+    let install = sqlx::query_as!(
+        InstallInfo,
+        r#"--sql
+        SELECT
+            created_at AS "created_at: DatabaseDateTime",
+            rotated_at AS "rotated_at: DatabaseDateTime"
+        FROM installs
+        WHERE user_id = $1"#,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    let connections = get_connections_by_user_id(pool, user_id).await?;
+    let login_recaps = get_login_recaps(pool, user_id, RecapFilter::all()).await?;
+    let duty_recaps = get_duty_recaps(pool, user_id, RecapFilter::all()).await?;
+
+    let queue_sizes = sqlx::query_as!(
+        DbQueueSizeContribution,
+        r#"--sql
+        SELECT world_id, time AS "time: DatabaseDateTime", size
+        FROM queue_sizes
+        WHERE user_id = $1
+        ORDER BY world_id"#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let roulette_sizes = sqlx::query_as!(
+        DbRouletteSizeContribution,
+        r#"--sql
+        SELECT
+            datacenter_id, languages, roulette_id,
+            role AS "role: DbRouletteRole",
+            CASE WHEN size_user_id = $1 THEN size_time END AS "size_time: DatabaseDateTime",
+            CASE WHEN size_user_id = $1 THEN size END AS size,
+            CASE WHEN est_time_user_id = $1 THEN est_time_time END AS "est_time_time: DatabaseDateTime",
+            CASE WHEN est_time_user_id = $1 THEN est_time END AS est_time,
+            CASE WHEN wait_time_user_id = $1 THEN wait_time_time END AS "wait_time_time: DatabaseDateTime",
+            CASE WHEN wait_time_user_id = $1 THEN wait_time END AS wait_time
+        FROM roulette_sizes
+        WHERE size_user_id = $1 OR est_time_user_id = $1 OR wait_time_user_id = $1
+        ORDER BY datacenter_id, languages, roulette_id, role"#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(UserExport {
+        user_id,
+        exported_at: time::OffsetDateTime::now_utc().into(),
+        install,
+        connections,
+        login_recaps: login_recaps.recaps,
+        duty_recaps: duty_recaps.recaps,
+        queue_sizes: queue_sizes
+            .into_iter()
+            .map(QueueSizeContribution::from)
+            .collect(),
+        roulette_sizes: roulette_sizes
+            .into_iter()
+            .map(RouletteSizeContribution::from)
+            .collect(),
+    })
+}
+
+// Everything is removed in one transaction. Rows that other users' estimates
+// still depend on are anonymized instead of deleted.
+pub async fn erase_user(pool: &PgPool, user_id: Uuid) -> Result<UserErasure, Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        r#"UPDATE queue_sizes SET user_id = $2 WHERE user_id = $1"#,
+        user_id,
+        Uuid::nil()
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"--sql
+        UPDATE roulette_sizes SET
+            size_user_id = NULLIF(size_user_id, $1),
+            est_time_user_id = NULLIF(est_time_user_id, $1),
+            wait_time_user_id = NULLIF(wait_time_user_id, $1)
+        WHERE size_user_id = $1 OR est_time_user_id = $1 OR wait_time_user_id = $1"#,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    // Positions, updates, pops, quarantine entries and compositions cascade
+    let login_recap_count = sqlx::query!(r#"DELETE FROM recaps WHERE user_id = $1"#, user_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+    let duty_recap_count = sqlx::query!(r#"DELETE FROM duty_recaps WHERE user_id = $1"#, user_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+    let connection_ids = sqlx::query_scalar!(
+        r#"DELETE FROM connections WHERE user_id = $1 RETURNING conn_user_id"#,
+        user_id
+    )
+    .fetch_all(&mut *tx)
+    .await?
+    .into_iter()
+    .map(|id| DatabaseU64::from(id).0)
+    .collect();
+
+    sqlx::query!(r#"DELETE FROM installs WHERE user_id = $1"#, user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(UserErasure {
+        connection_ids,
+        login_recap_count,
+        duty_recap_count,
+    })
+}
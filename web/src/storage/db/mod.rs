@@ -1,7 +1,12 @@
+pub mod client_versions;
 pub mod connections;
 pub mod duty;
+pub mod duty_stats;
+pub mod installs;
 pub mod job_info;
 pub mod login;
+pub mod me;
+pub mod subscriptions;
 pub mod summary;
 pub mod travel;
 pub mod world_info;
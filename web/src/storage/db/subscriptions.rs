@@ -0,0 +1,333 @@
+use super::wrappers::{DatabaseDateTime, DatabaseU64};
+use crate::models::subscription::{
+    DbClaimedSubscription, DbSubscription, DbSubscriptionEndpoint, DbSubscriptionState,
+    DeliveryOutcome,
+};
+use sqlx::{Error, PgPool};
+use uuid::Uuid;
+
+// Returns false if the user is already subscribed to the endpoint
+pub async fn create_subscription(
+    pool: &PgPool,
+    endpoint_type: DbSubscriptionEndpoint,
+    endpoint_id: i16,
+    discord_user_id: u64,
+    persistent: bool,
+    cooldown_secs: i32,
+    expires_at: Option<DatabaseDateTime>,
+) -> Result<bool, Error> {
+    sqlx::query!(
+        r#"--sql
+        INSERT INTO travel_subscriptions
+        (id, endpoint_type, endpoint_id, discord_user_id, persistent, cooldown_secs, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT (endpoint_type, endpoint_id, discord_user_id)
+            WHERE state IN ('active', 'pending')
+            DO NOTHING"#,
+        Uuid::now_v7(),
+        endpoint_type as DbSubscriptionEndpoint,
+        endpoint_id,
+        DatabaseU64(discord_user_id).as_db(),
+        persistent,
+        cooldown_secs,
+        expires_at.map(|t| t.as_db())
+    )
+    .execute(pool)
+    .await
+    .map(|r| r.rows_affected() != 0)
+}
+
+// Returns false if the user wasn't subscribed to the endpoint. Reminders that
+// are being delivered are cancelled instead of deleted, since the delivery
+// still has to be recorded against them.
+pub async fn delete_subscription(
+    pool: &PgPool,
+    endpoint_type: DbSubscriptionEndpoint,
+    endpoint_id: i16,
+    discord_user_id: u64,
+) -> Result<bool, Error> {
+    sqlx::query_scalar!(
+        r#"--sql
+        WITH deleted AS (
+            DELETE FROM travel_subscriptions
+            WHERE endpoint_type = $1
+            AND endpoint_id = $2
+            AND discord_user_id = $3
+            AND state = 'active'
+            RETURNING id
+        ),
+        cancelled AS (
+            UPDATE travel_subscriptions
+            SET state = 'cancelled', next_attempt_at = NULL
+            WHERE endpoint_type = $1
+            AND endpoint_id = $2
+            AND discord_user_id = $3
+            AND state = 'pending'
+            RETURNING id
+        )
+        SELECT (SELECT COUNT(*) FROM deleted) + (SELECT COUNT(*) FROM cancelled) AS "count!""#,
+        endpoint_type as DbSubscriptionEndpoint,
+        endpoint_id,
+        DatabaseU64(discord_user_id).as_db()
+    )
+    .fetch_one(pool)
+    .await
+    .map(|count| count != 0)
+}
+
+// Subscriptions that haven't been delivered yet, oldest first
+pub async fn get_subscriptions_by_discord_user_id(
+    pool: &PgPool,
+    discord_user_id: u64,
+) -> Result<Vec<DbSubscription>, Error> {
+    sqlx::query_as!(
+        DbSubscription,
+        r#"--sql
+        SELECT
+            endpoint_type AS "endpoint_type: DbSubscriptionEndpoint",
+            endpoint_id,
+            state AS "state: DbSubscriptionState",
+            created_at AS "created_at: DatabaseDateTime",
+            persistent,
+            cooldown_secs,
+            expires_at AS "expires_at: DatabaseDateTime"
+        FROM travel_subscriptions
+        WHERE discord_user_id = $1
+        AND state IN ('active', 'pending')
+        ORDER BY created_at"#,
+        DatabaseU64(discord_user_id).as_db()
+    )
+    .fetch_all(pool)
+    .await
+}
+
+// Pending subscriptions are cancelled rather than deleted, like in delete_subscription
+pub async fn delete_subscriptions_by_discord_user_id(
+    pool: &PgPool,
+    discord_user_id: u64,
+) -> Result<u64, Error> {
+    sqlx::query_scalar!(
+        r#"--sql
+        WITH deleted AS (
+            DELETE FROM travel_subscriptions
+            WHERE discord_user_id = $1
+            AND state = 'active'
+            RETURNING id
+        ),
+        cancelled AS (
+            UPDATE travel_subscriptions
+            SET state = 'cancelled', next_attempt_at = NULL
+            WHERE discord_user_id = $1
+            AND state = 'pending'
+            RETURNING id
+        )
+        SELECT (SELECT COUNT(*) FROM deleted) + (SELECT COUNT(*) FROM cancelled) AS "count!""#,
+        DatabaseU64(discord_user_id).as_db()
+    )
+    .fetch_one(pool)
+    .await
+    .map(|count| count.try_into().unwrap_or_default())
+}
+
+// Moves every waiting subscription of the endpoint to the delivery queue. The
+// claimed subscriptions won't be retried until the lease is up. Persistent
+// subscriptions are only claimed when the endpoint just reopened and their
+// cooldown is over.
+pub async fn claim_endpoint_subscriptions(
+    pool: &PgPool,
+    endpoint_type: DbSubscriptionEndpoint,
+    endpoint_id: i16,
+    reopened: bool,
+    lease: time::Duration,
+) -> Result<Vec<DbClaimedSubscription>, Error> {
+    sqlx::query_as!(
+        DbClaimedSubscription,
+        r#"--sql
+        UPDATE travel_subscriptions
+        SET state = 'pending',
+            next_attempt_at = (NOW() AT TIME ZONE 'UTC') + make_interval(secs => $3)
+        WHERE endpoint_type = $1
+        AND endpoint_id = $2
+        AND state = 'active'
+        AND (expires_at IS NULL OR expires_at > (NOW() AT TIME ZONE 'UTC'))
+        AND (
+            NOT persistent
+            OR (
+                $4
+                AND (
+                    last_notified_at IS NULL
+                    OR last_notified_at + make_interval(secs => cooldown_secs) <= (NOW() AT TIME ZONE 'UTC')
+                )
+            )
+        )
+        RETURNING
+            id,
+            endpoint_type AS "endpoint_type: DbSubscriptionEndpoint",
+            endpoint_id,
+            discord_user_id,
+            attempts,
+            persistent"#,
+        endpoint_type as DbSubscriptionEndpoint,
+        endpoint_id,
+        lease.as_seconds_f64(),
+        reopened
+    )
+    .fetch_all(pool)
+    .await
+}
+
+// Claims pending subscriptions whose next attempt is due
+pub async fn claim_due_subscriptions(
+    pool: &PgPool,
+    limit: i64,
+    lease: time::Duration,
+) -> Result<Vec<DbClaimedSubscription>, Error> {
+    sqlx::query_as!(
+        DbClaimedSubscription,
+        r#"--sql
+        UPDATE travel_subscriptions
+        SET next_attempt_at = (NOW() AT TIME ZONE 'UTC') + make_interval(secs => $2)
+        WHERE id IN (
+            SELECT id
+            FROM travel_subscriptions
+            WHERE state = 'pending'
+            AND next_attempt_at <= (NOW() AT TIME ZONE 'UTC')
+            ORDER BY next_attempt_at
+            LIMIT $1
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING
+            id,
+            endpoint_type AS "endpoint_type: DbSubscriptionEndpoint",
+            endpoint_id,
+            discord_user_id,
+            attempts,
+            persistent"#,
+        limit,
+        lease.as_seconds_f64()
+    )
+    .fetch_all(pool)
+    .await
+}
+
+// Expires waiting subscriptions that are past their expiry. Pending ones are
+// left alone so reminders that are already on their way still get delivered.
+// Returns the number of subscriptions expired.
+pub async fn expire_subscriptions(pool: &PgPool) -> Result<u64, Error> {
+    sqlx::query!(
+        r#"--sql
+        UPDATE travel_subscriptions
+        SET state = 'expired'
+        WHERE state = 'active'
+        AND expires_at <= (NOW() AT TIME ZONE 'UTC')"#
+    )
+    .execute(pool)
+    .await
+    .map(|r| r.rows_affected())
+}
+
+// Puts a claimed subscription back to waiting, for when the endpoint closed
+// again before the reminder could be delivered
+pub async fn rearm_subscription(pool: &PgPool, id: Uuid) -> Result<(), Error> {
+    sqlx::query!(
+        r#"--sql
+        UPDATE travel_subscriptions
+        SET state = 'active', next_attempt_at = NULL
+        WHERE id = $1 AND state = 'pending'"#,
+        id
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn record_delivery(
+    pool: &PgPool,
+    id: Uuid,
+    attempt: i16,
+    outcome: DeliveryOutcome,
+) -> Result<(), Error> {
+    let mut tx = pool.begin().await?;
+
+    let error = match &outcome {
+        DeliveryOutcome::Delivered => None,
+        DeliveryOutcome::Retry { error, .. } => Some(error.as_str()),
+        DeliveryOutcome::Disabled { reason } => Some(reason.as_str()),
+    };
+    // Persistent subscriptions reset their attempts after every reminder, so
+    // the log numbers its entries on its own. Cancelled subscriptions still get
+    // their delivery logged, but are otherwise left alone.
+    sqlx::query!(
+        r#"--sql
+        INSERT INTO travel_subscription_deliveries
+        (subscription_id, attempt, time, success, error)
+        SELECT $1, COALESCE(MAX(attempt), 0) + 1, NOW() AT TIME ZONE 'UTC', $2, $3
+        FROM travel_subscription_deliveries
+        WHERE subscription_id = $1
+        ON CONFLICT (subscription_id, attempt) DO NOTHING"#,
+        id,
+        error.is_none(),
+        error
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    match &outcome {
+        // Persistent subscriptions start waiting for the next opening again
+        DeliveryOutcome::Delivered => {
+            sqlx::query!(
+                r#"--sql
+                UPDATE travel_subscriptions
+                SET state = CASE WHEN persistent
+                        THEN 'active'::subscription_state
+                        ELSE 'delivered'::subscription_state
+                    END,
+                    attempts = CASE WHEN persistent THEN 0::smallint ELSE $2 END,
+                    next_attempt_at = NULL,
+                    last_error = CASE WHEN persistent THEN NULL ELSE last_error END,
+                    delivered_at = NOW() AT TIME ZONE 'UTC',
+                    last_notified_at = NOW() AT TIME ZONE 'UTC'
+                WHERE id = $1 AND state = 'pending'"#,
+                id,
+                attempt
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        DeliveryOutcome::Retry { error, retry_at } => {
+            sqlx::query!(
+                r#"--sql
+                UPDATE travel_subscriptions
+                SET attempts = $2,
+                    next_attempt_at = $3,
+                    last_error = $4
+                WHERE id = $1 AND state = 'pending'"#,
+                id,
+                attempt,
+                retry_at.as_db(),
+                error
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        DeliveryOutcome::Disabled { reason } => {
+            sqlx::query!(
+                r#"--sql
+                UPDATE travel_subscriptions
+                SET state = 'disabled',
+                    attempts = $2,
+                    next_attempt_at = NULL,
+                    last_error = $3,
+                    disabled_reason = $3
+                WHERE id = $1 AND state = 'pending'"#,
+                id,
+                attempt,
+                reason
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+
+    tx.commit().await
+}
@@ -1,5 +1,12 @@
-use super::wrappers::DatabaseU16;
-use crate::models::travel::{DCTravelWorldInfo, DbTravelState};
+use super::wrappers::{DatabaseDateTime, DatabaseU16};
+use crate::models::{
+    travel::{
+        DCTravelWorldInfo, DbTravelClosure, DbTravelState, DbTravelStateChange, TravelHistory,
+        TravelHistoryRange,
+    },
+    WorldQueryFilter,
+};
+use itertools::Itertools;
 use sqlx::{Error, PgPool, QueryBuilder};
 use std::collections::HashMap;
 
@@ -142,3 +149,112 @@ pub async fn get_travel_states_by_world_id(
     .map(|s| (s.world_id as u16, s.prohibit))
     .collect::<HashMap<_, _>>())
 }
+
+pub async fn get_travel_history(
+    pool: &PgPool,
+    filter: WorldQueryFilter,
+    range: TravelHistoryRange,
+) -> Result<Vec<TravelHistory>, Error> {
+    let to_db = |ids: Option<Vec<u16>>| {
+        ids.map(|ids| {
+            ids.into_iter()
+                .map(|id| DatabaseU16(id).as_db())
+                .collect::<Vec<_>>()
+        })
+    };
+    let region_ids = to_db(filter.region_id);
+    let datacenter_ids = to_db(filter.datacenter_id);
+    let world_ids = to_db(filter.world_id);
+
+    let states = sqlx::query_as!(
+        DbTravelStateChange,
+        r#"--sql
+        WITH filtered_worlds AS (
+            SELECT world_id
+            FROM worlds
+            WHERE ($3::smallint[] IS NULL OR region_id = ANY($3))
+            AND ($4::smallint[] IS NULL OR datacenter_id = ANY($4))
+            AND ($5::smallint[] IS NULL OR world_id = ANY($5))
+        )
+        SELECT world_id AS "world_id!", time AS "time!: DatabaseDateTime", prohibit AS "prohibit!"
+        FROM (
+            SELECT s.world_id, s.time, s.prohibit
+            FROM filtered_worlds w
+            CROSS JOIN LATERAL (
+                SELECT world_id, time, prohibit
+                FROM travel_states t
+                WHERE t.world_id = w.world_id
+                AND t.time < $1
+                ORDER BY t.time DESC
+                LIMIT 1
+            ) s
+            UNION ALL
+            SELECT t.world_id, t.time, t.prohibit
+            FROM travel_states t
+            JOIN filtered_worlds w USING (world_id)
+            WHERE t.time >= $1
+            AND t.time < $2
+        ) s
+        ORDER BY world_id, time"#,
+        range.start.as_db(),
+        range.end.as_db(),
+        region_ids.as_deref(),
+        datacenter_ids.as_deref(),
+        world_ids.as_deref()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(states
+        .into_iter()
+        .chunk_by(|s| s.world_id)
+        .into_iter()
+        .map(|(world_id, states)| {
+            TravelHistory::new(DatabaseU16::from(world_id).0, &states.collect_vec(), range)
+        })
+        .collect())
+}
+
+// Periods where travel was prohibited, collapsed from the transition log
+pub async fn get_travel_closures(
+    pool: &PgPool,
+    world_ids: Vec<u16>,
+    since: DatabaseDateTime,
+) -> Result<Vec<DbTravelClosure>, Error> {
+    let world_ids = world_ids
+        .into_iter()
+        .map(|id| DatabaseU16(id).as_db())
+        .collect::<Vec<_>>();
+    sqlx::query_as!(
+        DbTravelClosure,
+        r#"--sql
+        WITH changes AS (
+            SELECT
+                world_id, time, prohibit,
+                LAG(prohibit) OVER (PARTITION BY world_id ORDER BY time) AS prev_prohibit
+            FROM travel_states
+            WHERE world_id = ANY($1)
+            AND time >= $2
+        ),
+        transitions AS (
+            SELECT
+                world_id, time, prohibit,
+                prev_prohibit IS NULL AS truncated,
+                LEAD(time) OVER (PARTITION BY world_id ORDER BY time) AS next_time
+            FROM changes
+            WHERE prev_prohibit IS DISTINCT FROM prohibit
+        )
+        SELECT
+            world_id AS "world_id!",
+            time AS "start!: DatabaseDateTime",
+            next_time AS "end: DatabaseDateTime",
+            truncated AS "truncated!"
+        FROM transitions
+        WHERE prohibit
+        ORDER BY world_id, time"#,
+        world_ids.as_slice(),
+        since.as_db()
+    )
+    .fetch_all(pool)
+    .await
+}
@@ -20,6 +20,20 @@ pub struct XivApiRow<T> {
     pub fields: T,
 }
 
+#[derive(Debug, Deserialize)]
+#[allow(unused)]
+pub struct XivApiSheet<T> {
+    pub schema: String,
+    pub rows: Vec<XivApiSheetRow<T>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[allow(unused)]
+pub struct XivApiSheetRow<T> {
+    pub row_id: u16,
+    pub fields: T,
+}
+
 #[derive(Debug, Deserialize)]
 #[allow(unused)]
 pub struct XivApiLink<T> {
@@ -72,6 +86,39 @@ pub async fn search_xivapi<T: DeserializeOwned>(
     Ok(ret)
 }
 
+pub async fn get_sheet_rows_xivapi<T: DeserializeOwned>(
+    client: &Client,
+    sheet: &str,
+    rows: &[u16],
+    fields: &str,
+) -> Result<Vec<Vec<XivApiSheetRow<T>>>, reqwest::Error> {
+    // Keeps the query string to a reasonable length
+    const CHUNK_SIZE: usize = 100;
+
+    let mut ret = vec![];
+    for chunk in rows.chunks(CHUNK_SIZE) {
+        let rows = chunk
+            .iter()
+            .map(u16::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+        let req = client
+            .get(format!("https://v2.xivapi.com/api/sheet/{sheet}"))
+            .query(&[("fields", fields), ("rows", &rows)])
+            .build()?;
+        let resp: XivApiSheet<T> = client
+            .execute(req.try_clone().unwrap())
+            .await?
+            .json()
+            .await
+            .inspect_err(|e| {
+                log::error!("Failed {} due to {:?}", req.url(), e);
+            })?;
+        ret.push(resp.rows);
+    }
+    Ok(ret)
+}
+
 pub fn get_icon_url_from_id(icon_id: u32) -> String {
     get_icon_url(format!("ui/icon/{:03}000/{:06}_hr1.tex", icon_id / 1000, icon_id).as_str())
 }
@@ -1,10 +1,10 @@
 use std::collections::HashMap;
 
 use super::{
-    api::{GameSheet, XivApiIcon, search_xivapi},
+    api::{GameSheet, XivApiIcon, XivApiLink, get_sheet_rows_xivapi, search_xivapi},
     impl_game_data,
 };
-use crate::stopwatch::Stopwatch;
+use crate::{models::duty::PartyRoles, stopwatch::Stopwatch, storage::db};
 use reqwest::Client;
 use serde::Deserialize;
 use serenity::async_trait;
@@ -14,6 +14,7 @@ use sqlx::PgPool;
 #[serde(rename_all = "PascalCase")]
 struct XivApiContentRoulette {
     pub category: String,
+    pub content_member_type: XivApiLink<XivApiContentMemberType>,
     pub image: XivApiIcon,
     pub name: String,
 }
@@ -21,10 +22,38 @@ struct XivApiContentRoulette {
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 struct XivApiContentFinderCondition {
+    pub content_member_type: XivApiLink<XivApiContentMemberType>,
     pub image: XivApiIcon,
     pub name: String,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct XivApiContentMemberType {
+    pub tanks_per_party: u8,
+    pub healers_per_party: u8,
+    pub melees_per_party: u8,
+    pub ranged_per_party: u8,
+}
+
+impl XivApiContentMemberType {
+    // None for content without role slots (solo duties, PvP, etc.)
+    fn party_roles(&self) -> Option<PartyRoles> {
+        let roles = PartyRoles {
+            tanks: self.tanks_per_party,
+            healers: self.healers_per_party,
+            dps: self.melees_per_party + self.ranged_per_party,
+        };
+        (roles.tanks + roles.healers + roles.dps > 0).then_some(roles)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct XivApiLogMessage {
+    pub text: String,
+}
+
 struct ContentRouletteSheet;
 struct ContentFinderConditionSheet;
 
@@ -38,7 +67,7 @@ impl GameSheet for ContentRouletteSheet {
             client,
             "ContentRoulette",
             "IsInDutyFinder=1",
-            "Name,Image,Category",
+            "Name,Image,Category,ContentMemberType.TanksPerParty,ContentMemberType.HealersPerParty,ContentMemberType.MeleesPerParty,ContentMemberType.RangedPerParty",
         )
         .await?
         .into_iter()
@@ -48,11 +77,13 @@ impl GameSheet for ContentRouletteSheet {
             let name = r.fields.name;
             let _category = r.fields.category;
             let image_path = r.fields.image.path_hr1;
+            let party_roles = r.fields.content_member_type.fields.party_roles();
             ContentRouletteInfo {
                 id,
                 name,
                 // category,
                 image_path,
+                party_roles,
             }
         })
         .collect())
@@ -69,7 +100,7 @@ impl GameSheet for ContentFinderConditionSheet {
             client,
             "ContentFinderCondition",
             "-Image=0",
-            "Name,Image",
+            "Name,Image,ContentMemberType.TanksPerParty,ContentMemberType.HealersPerParty,ContentMemberType.MeleesPerParty,ContentMemberType.RangedPerParty",
         )
         .await?
         .into_iter()
@@ -83,10 +114,12 @@ impl GameSheet for ContentFinderConditionSheet {
                 name.replace_range(..c.len_utf8(), &ch);
             }
             let image_path = r.fields.image.path_hr1;
+            let party_roles = r.fields.content_member_type.fields.party_roles();
             ContentFinderInfo {
                 id,
                 name,
                 image_path,
+                party_roles,
             }
         })
         .collect())
@@ -97,17 +130,25 @@ pub struct ContentRouletteInfo {
     pub id: u8,
     pub name: String,
     pub image_path: String,
+    pub party_roles: Option<PartyRoles>,
 }
 
 pub struct ContentFinderInfo {
     pub id: u16,
     pub name: String,
     pub image_path: String,
+    pub party_roles: Option<PartyRoles>,
+}
+
+pub struct WithdrawMessageInfo {
+    pub id: u16,
+    pub text: String,
 }
 
 pub struct ContentData {
     pub roulettes: HashMap<u8, ContentRouletteInfo>,
     pub content: HashMap<u16, ContentFinderInfo>,
+    pub withdraw_messages: HashMap<u16, WithdrawMessageInfo>,
 }
 
 impl ContentData {
@@ -126,9 +167,55 @@ impl ContentData {
                 .into_iter()
                 .map(|r| (r.id, r))
                 .collect(),
+            withdraw_messages: Self::get_withdraw_messages(pool, client).await,
         })
     }
 
+    // Withdraw reasons are regular log messages with nothing to search on, so
+    // only the ones recaps have reported are fetched. They're only shown as
+    // text, so on failure they fall back to "Unknown reason" instead
+    async fn get_withdraw_messages(
+        pool: &PgPool,
+        client: &Client,
+    ) -> HashMap<u16, WithdrawMessageInfo> {
+        let ids = match db::duty_stats::get_withdraw_message_ids(pool).await {
+            Ok(ids) => ids,
+            Err(e) => {
+                log::error!("Failed to get withdraw message ids: {}", e);
+                return HashMap::new();
+            }
+        };
+        match get_sheet_rows_xivapi::<XivApiLogMessage>(client, "LogMessage", &ids, "Text").await {
+            Ok(rows) => rows
+                .into_iter()
+                .flatten()
+                .filter(|r| !r.fields.text.is_empty())
+                .map(|r| WithdrawMessageInfo {
+                    id: r.row_id,
+                    text: r.fields.text,
+                })
+                .map(|m| (m.id, m))
+                .collect(),
+            Err(e) => {
+                log::error!("Failed to get withdraw messages: {}", e);
+                HashMap::new()
+            }
+        }
+    }
+
+    // Role slots per party, keyed by (roulette, 0) or (0, content)
+    pub fn get_party_roles(&self) -> Vec<(u8, u16, PartyRoles)> {
+        self.roulettes
+            .values()
+            .filter_map(|r| r.party_roles.map(|roles| (r.id, 0, roles)))
+            .chain(
+                self.content
+                    .values()
+                    .filter_map(|c| c.party_roles.map(|roles| (0, c.id, roles))),
+            )
+            .collect()
+    }
+
     pub fn get_roulette_by_id(&self, id: u8) -> Option<&ContentRouletteInfo> {
         self.roulettes.get(&id)
     }
@@ -137,6 +224,10 @@ impl ContentData {
         self.content.get(&id)
     }
 
+    pub fn get_withdraw_message_by_id(&self, id: u16) -> Option<&WithdrawMessageInfo> {
+        self.withdraw_messages.get(&id)
+    }
+
     pub fn get_roulette_name(&self, id: u8) -> String {
         self.get_roulette_by_id(id)
             .map_or_else(|| format!("Roulette {}", id), |r| r.name.clone())
@@ -147,6 +238,11 @@ impl ContentData {
             .map_or_else(|| format!("Content {}", id), |r| r.name.clone())
     }
 
+    pub fn get_withdraw_reason(&self, id: u16) -> String {
+        self.get_withdraw_message_by_id(id)
+            .map_or_else(|| format!("Unknown reason ({})", id), |r| r.text.clone())
+    }
+
     pub fn get_roulette_image(&self, id: u8) -> String {
         self.get_roulette_by_id(id)
             .map_or_else(|| Self::DEFAULT_IMAGE.to_string(), |r| r.image_path.clone())
@@ -3,7 +3,11 @@ use super::{
     api::{GameSheet, XivApiLink, search_xivapi},
     impl_game_data,
 };
-use crate::{models::world_info::WorldInfo, stopwatch::Stopwatch, storage::db};
+use crate::{
+    models::{WorldQueryFilter, world_info::WorldInfo},
+    stopwatch::Stopwatch,
+    storage::db,
+};
 use fuzzy_matcher::{FuzzyMatcher, skim::SkimMatcherV2};
 use itertools::Itertools;
 use poise::ChoiceParameter;
@@ -243,6 +247,23 @@ impl WorldData {
     pub fn get_world_by_id(&self, id: u16) -> Option<&World> {
         self.worlds.iter().find(|world| world.id == id)
     }
+
+    pub fn filter_worlds(&self, filter: &WorldQueryFilter) -> Vec<&World> {
+        self.worlds
+            .iter()
+            .filter(|world| {
+                if let Some(region_ids) = &filter.region_id {
+                    region_ids.contains(&world.datacenter.region_id)
+                } else if let Some(datacenter_ids) = &filter.datacenter_id {
+                    datacenter_ids.contains(&world.datacenter.id)
+                } else if let Some(world_ids) = &filter.world_id {
+                    world_ids.contains(&world.id)
+                } else {
+                    true
+                }
+            })
+            .collect_vec()
+    }
 }
 
 impl_game_data!(WorldData, WORLD_DATA);
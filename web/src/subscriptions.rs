@@ -1,7 +1,14 @@
 use crate::{
     discord::{commands::create_travel_embed, utils::COLOR_SUCCESS, DiscordClient},
+    models::subscription::{
+        DbClaimedSubscription, DbSubscriptionEndpoint, DbSubscriptionState, DeliveryOutcome,
+    },
     storage::{
-        game::worlds::{Datacenter, World},
+        db::{
+            self,
+            wrappers::{DatabaseDateTime, DatabaseU64},
+        },
+        game::worlds::{self, Datacenter, World},
         redis::{
             client::RedisClient,
             utils::{RedisKey, RedisValue},
@@ -9,19 +16,51 @@ use crate::{
     },
 };
 use futures_util::{stream, StreamExt};
-use redis::{AsyncCommands, Cmd};
+use itertools::Itertools;
+use redis::AsyncCommands;
 use serde::{Deserialize, Serialize};
-use serenity::all::{CreateMessage, UserId};
-use std::sync::Arc;
+use serenity::all::{
+    CreateEmbedFooter, CreateMessage, DiscordJsonError, ErrorResponse, HttpError, UserId,
+};
+use sqlx::PgPool;
+use std::{collections::HashMap, sync::Arc};
+
+// Claimed subscriptions are retried after this long if the delivery never finishes
+const DELIVERY_LEASE: time::Duration = time::Duration::minutes(5);
+const RETRY_BATCH_SIZE: i64 = 64;
+const MAX_ATTEMPTS: i16 = 8;
+const BASE_BACKOFF: time::Duration = time::Duration::seconds(30);
+const MAX_BACKOFF: time::Duration = time::Duration::hours(1);
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
-    #[error("Serenity error")]
+    #[error("Serenity error: {0}")]
     Serenity(#[from] serenity::Error),
-    #[error("Redis error")]
+    #[error("Redis error: {0}")]
     Redis(#[from] redis::RedisError),
-    #[error("Postcard error")]
+    #[error("Postcard error: {0}")]
     Postcard(#[from] postcard::Error),
+    #[error("Database error: {0}")]
+    Sqlx(#[from] sqlx::Error),
+}
+
+impl Error {
+    // Retrying won't help, so the subscription should be disabled
+    fn is_permanent(&self) -> bool {
+        matches!(
+            self,
+            Self::Serenity(serenity::Error::Http(HttpError::UnsuccessfulRequest(
+                ErrorResponse {
+                    error: DiscordJsonError {
+                        // Cannot send messages to this user, Unknown user
+                        code: 50007 | 10013,
+                        ..
+                    },
+                    ..
+                }
+            )))
+        )
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -39,10 +78,30 @@ pub enum Endpoint {
     World(u16),
 }
 
+// Subscriptions used to be stored in redis sets under this key
 impl RedisKey for Endpoint {
     const PREFIX: &'static str = "subscriptions";
 }
 
+impl Endpoint {
+    fn as_db(self) -> (DbSubscriptionEndpoint, i16) {
+        #[allow(clippy::cast_possible_wrap)]
+        match self {
+            Endpoint::Datacenter(id) => (DbSubscriptionEndpoint::Datacenter, id as i16),
+            Endpoint::World(id) => (DbSubscriptionEndpoint::World, id as i16),
+        }
+    }
+
+    fn from_db(endpoint_type: DbSubscriptionEndpoint, id: i16) -> Self {
+        #[allow(clippy::cast_sign_loss)]
+        let id = id as u16;
+        match endpoint_type {
+            DbSubscriptionEndpoint::Datacenter => Endpoint::Datacenter(id),
+            DbSubscriptionEndpoint::World => Endpoint::World(id),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct EndpointPublishData(pub Arc<EndpointPublish>);
 
@@ -65,6 +124,23 @@ pub enum EndpointPublish {
     },
 }
 
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SubscriptionOptions {
+    // Keep the subscription after reminding, waiting at least this long
+    // before reminding again. None makes the subscription one-shot.
+    pub repeat_cooldown: Option<time::Duration>,
+    pub expires_at: Option<DatabaseDateTime>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SubscriptionInfo {
+    pub endpoint: Endpoint,
+    pub created_at: DatabaseDateTime,
+    // The endpoint opened and the reminder is still being delivered
+    pub is_pending: bool,
+    pub options: SubscriptionOptions,
+}
+
 impl From<&EndpointPublish> for Endpoint {
     fn from(endpoint: &EndpointPublish) -> Self {
         match endpoint {
@@ -95,21 +171,39 @@ impl SubscriptionManager {
         self.imp.discord.redis()
     }
 
+    #[must_use]
+    fn db(&self) -> &PgPool {
+        self.imp.discord.db()
+    }
+
     pub async fn subscribe(
         &self,
         endpoint: Endpoint,
         subscriber: Subscriber,
+        options: SubscriptionOptions,
     ) -> Result<bool, Error> {
-        let ret = self
-            .redis()
-            .clone()
-            .sadd(
-                endpoint.to_key(self.redis().config())?,
-                subscriber.to_value()?,
-            )
-            .await?;
+        let (endpoint_type, endpoint_id) = endpoint.as_db();
+        let Subscriber::Discord(user_id) = subscriber;
+        let cooldown_secs = options.repeat_cooldown.map_or(0, |cooldown| {
+            i32::try_from(cooldown.whole_seconds()).unwrap_or(i32::MAX)
+        });
+        let ret = db::subscriptions::create_subscription(
+            self.db(),
+            endpoint_type,
+            endpoint_id,
+            user_id,
+            options.repeat_cooldown.is_some(),
+            cooldown_secs,
+            options.expires_at,
+        )
+        .await?;
         if ret {
-            log::info!("User {:?} subscribed to {:?}", subscriber, endpoint);
+            log::info!(
+                "User {:?} subscribed to {:?} ({:?})",
+                subscriber,
+                endpoint,
+                options
+            );
         }
         Ok(ret)
     }
@@ -119,72 +213,235 @@ impl SubscriptionManager {
         endpoint: Endpoint,
         subscriber: &Subscriber,
     ) -> Result<bool, Error> {
-        let ret = self
-            .redis()
-            .clone()
-            .srem(
-                endpoint.to_key(self.redis().config())?,
-                subscriber.to_value()?,
-            )
-            .await?;
+        let (endpoint_type, endpoint_id) = endpoint.as_db();
+        let Subscriber::Discord(user_id) = subscriber;
+        let ret =
+            db::subscriptions::delete_subscription(self.db(), endpoint_type, endpoint_id, *user_id)
+                .await?;
         if ret {
             log::info!("User {:?} unsubscribed from {:?}", subscriber, endpoint);
         }
         Ok(ret)
     }
 
-    /// Publishing errors will be printed to the log.
-    pub async fn publish_endpoint(&self, publish_data: EndpointPublish) -> Result<(), Error> {
-        const CHUNK_SIZE: usize = 32;
+    pub async fn get_subscriptions(
+        &self,
+        subscriber: &Subscriber,
+    ) -> Result<Vec<SubscriptionInfo>, Error> {
+        let Subscriber::Discord(user_id) = subscriber;
+        Ok(
+            db::subscriptions::get_subscriptions_by_discord_user_id(self.db(), *user_id)
+                .await?
+                .into_iter()
+                .map(|s| SubscriptionInfo {
+                    endpoint: Endpoint::from_db(s.endpoint_type, s.endpoint_id),
+                    created_at: s.created_at,
+                    is_pending: s.state == DbSubscriptionState::Pending,
+                    options: SubscriptionOptions {
+                        repeat_cooldown: s
+                            .persistent
+                            .then(|| time::Duration::seconds(s.cooldown_secs.into())),
+                        expires_at: s.expires_at,
+                    },
+                })
+                .collect(),
+        )
+    }
 
+    // Returns the number of subscriptions removed
+    pub async fn unsubscribe_all(&self, subscriber: &Subscriber) -> Result<u64, Error> {
+        let Subscriber::Discord(user_id) = subscriber;
+        let ret =
+            db::subscriptions::delete_subscriptions_by_discord_user_id(self.db(), *user_id).await?;
+        if ret != 0 {
+            log::info!("User {:?} unsubscribed from {} endpoints", subscriber, ret);
+        }
+        Ok(ret)
+    }
+
+    /// Delivery errors will be printed to the log. Failed deliveries are
+    /// retried by [`SubscriptionManager::retry_deliveries`].
+    ///
+    /// `reopened` should only be set when the endpoint was closed before;
+    /// persistent subscriptions are skipped otherwise.
+    pub async fn publish_endpoint(
+        &self,
+        publish_data: EndpointPublish,
+        reopened: bool,
+    ) -> Result<(), Error> {
         let publish_data: EndpointPublishData = publish_data.into();
-        let endpoint: Endpoint = (&*publish_data.0).into();
+        let (endpoint_type, endpoint_id) = Endpoint::from(&*publish_data.0).as_db();
 
-        let key = endpoint.to_key(self.redis().config())?;
-        let mut redis = self.redis().clone();
+        let subscriptions = db::subscriptions::claim_endpoint_subscriptions(
+            self.db(),
+            endpoint_type,
+            endpoint_id,
+            reopened,
+            DELIVERY_LEASE,
+        )
+        .await?;
+
+        stream::iter(subscriptions)
+            .for_each_concurrent(None, |subscription| {
+                let data = publish_data.clone();
+                async move {
+                    self.deliver(subscription, &data.0).await;
+                }
+            })
+            .await;
+
+        Ok(())
+    }
 
-        loop {
-            let subscribers: Vec<Vec<u8>> = Cmd::spop(key.clone())
-                .arg(CHUNK_SIZE)
-                .query_async(&mut redis)
+    // Retries deliveries that are due. Reminders for endpoints that closed
+    // again in the meantime go back to waiting for the next opening.
+    pub async fn retry_deliveries(&self) -> Result<(), Error> {
+        let subscriptions =
+            db::subscriptions::claim_due_subscriptions(self.db(), RETRY_BATCH_SIZE, DELIVERY_LEASE)
                 .await?;
-            if subscribers.is_empty() {
-                break;
-            }
-            let should_break = subscribers.len() < CHUNK_SIZE;
-            stream::iter(subscribers)
-                .for_each_concurrent(None, |subscriber| {
-                    let data = publish_data.clone();
-                    async move {
-                        let subscriber = match Subscriber::from_value(&subscriber) {
-                            Ok(subscriber) => subscriber,
-                            Err(e) => {
+
+        let endpoints = subscriptions
+            .iter()
+            .map(|s| Endpoint::from_db(s.endpoint_type, s.endpoint_id))
+            .unique()
+            .collect_vec();
+        let mut publish_data: HashMap<Endpoint, Option<EndpointPublishData>> = HashMap::new();
+        for endpoint in endpoints {
+            let data = self.get_open_endpoint(endpoint).await?;
+            publish_data.insert(endpoint, data.map(EndpointPublishData::from));
+        }
+
+        stream::iter(subscriptions)
+            .for_each_concurrent(None, |subscription| {
+                let endpoint =
+                    Endpoint::from_db(subscription.endpoint_type, subscription.endpoint_id);
+                let data = publish_data.get(&endpoint).cloned().flatten();
+                async move {
+                    match data {
+                        Some(data) => self.deliver(subscription, &data.0).await,
+                        None => {
+                            if let Err(e) =
+                                db::subscriptions::rearm_subscription(self.db(), subscription.id)
+                                    .await
+                            {
                                 log::error!(
-                                    "Failed to deserialize subscriber: {} (data = {:?})",
-                                    e,
-                                    subscriber
+                                    "Failed to rearm subscription {}: {}",
+                                    subscription.id,
+                                    e
                                 );
-                                return;
                             }
-                        };
-                        if let Err(e) = self.publish_to(&subscriber, &data.0).await {
-                            log::error!("Failed to publish to {:?}: {}", subscriber, e);
                         }
                     }
+                }
+            })
+            .await;
+
+        Ok(())
+    }
+
+    // Returns the number of subscriptions expired
+    pub async fn expire_subscriptions(&self) -> Result<u64, Error> {
+        let ret = db::subscriptions::expire_subscriptions(self.db()).await?;
+        if ret != 0 {
+            log::info!("Expired {} subscriptions", ret);
+        }
+        Ok(ret)
+    }
+
+    // Returns None if travel to the endpoint is prohibited
+    async fn get_open_endpoint(
+        &self,
+        endpoint: Endpoint,
+    ) -> Result<Option<EndpointPublish>, Error> {
+        let travel_data = worlds::get_data();
+        Ok(match endpoint {
+            Endpoint::Datacenter(id) => {
+                let Some(data) = travel_data.get_datacenter_by_id(id) else {
+                    return Ok(None);
+                };
+                let states =
+                    db::travel::get_travel_states_by_datacenter_id(self.db(), vec![id]).await?;
+                if states.values().all(|prohibited| *prohibited) {
+                    return Ok(None);
+                }
+                Some(EndpointPublish::Datacenter {
+                    id,
+                    data,
+                    worlds: states
+                        .into_iter()
+                        .filter_map(|(world_id, prohibited)| {
+                            travel_data
+                                .get_world_by_id(world_id)
+                                .map(|w| (w, prohibited))
+                        })
+                        .collect(),
                 })
-                .await;
-            if should_break {
-                break;
             }
-        }
+            Endpoint::World(id) => {
+                let Some(data) = travel_data.get_world_by_id(id) else {
+                    return Ok(None);
+                };
+                let prohibited = db::travel::get_travel_states_by_world_id(self.db(), vec![id])
+                    .await?
+                    .get(&id)
+                    .copied()
+                    .unwrap_or(true);
+                (!prohibited).then_some(EndpointPublish::World { id, data })
+            }
+        })
+    }
 
-        Ok(())
+    async fn deliver(&self, subscription: DbClaimedSubscription, publish_data: &EndpointPublish) {
+        let subscriber = Subscriber::Discord(DatabaseU64::from(subscription.discord_user_id).0);
+        let attempt = subscription.attempts.saturating_add(1);
+
+        let outcome = match self
+            .publish_to(&subscriber, publish_data, subscription.persistent)
+            .await
+        {
+            Ok(()) => DeliveryOutcome::Delivered,
+            Err(e) if e.is_permanent() => {
+                log::warn!("Disabling subscription for {:?}: {}", subscriber, e);
+                DeliveryOutcome::Disabled {
+                    reason: e.to_string(),
+                }
+            }
+            Err(e) if attempt >= MAX_ATTEMPTS => {
+                log::warn!(
+                    "Giving up on {:?} after {} attempts: {}",
+                    subscriber,
+                    attempt,
+                    e
+                );
+                DeliveryOutcome::Disabled {
+                    reason: format!("Gave up after {attempt} attempts: {e}"),
+                }
+            }
+            Err(e) => {
+                log::error!("Failed to publish to {:?}: {}", subscriber, e);
+                DeliveryOutcome::Retry {
+                    error: e.to_string(),
+                    retry_at: (time::OffsetDateTime::now_utc() + backoff(attempt)).into(),
+                }
+            }
+        };
+
+        if let Err(e) =
+            db::subscriptions::record_delivery(self.db(), subscription.id, attempt, outcome).await
+        {
+            log::error!(
+                "Failed to record delivery for subscription {}: {}",
+                subscription.id,
+                e
+            );
+        }
     }
 
     async fn publish_to(
         &self,
         subscriber: &Subscriber,
         publish_data: &EndpointPublish,
+        persistent: bool,
     ) -> Result<(), Error> {
         match subscriber {
             Subscriber::Discord(user_id) => {
@@ -196,16 +453,31 @@ impl SubscriptionManager {
                         worlds,
                     } => (
                         &data.to_string(),
-                        create_travel_embed(&data.to_string(), worlds.clone(), config),
+                        create_travel_embed(
+                            &data.to_string(),
+                            worlds.clone(),
+                            &HashMap::new(),
+                            config,
+                        ),
                     ),
                     EndpointPublish::World { id: _, data } => (
                         &data.to_string(),
-                        create_travel_embed(&data.to_string(), vec![(data, false)], config),
+                        create_travel_embed(
+                            &data.to_string(),
+                            vec![(data, false)],
+                            &HashMap::new(),
+                            config,
+                        ),
                     ),
                 };
-                let embed = embed
+                let mut embed = embed
                     .title(format!("{name} is now available for DC Travel"))
                     .color(COLOR_SUCCESS);
+                if persistent {
+                    embed = embed.footer(CreateEmbedFooter::new(
+                        "You will be reminded again the next time it opens. Use /remindoff to stop.",
+                    ));
+                }
 
                 UserId::new(*user_id)
                     .dm(&self.imp.discord.http(), CreateMessage::new().embed(embed))
@@ -214,4 +486,75 @@ impl SubscriptionManager {
         };
         Ok(())
     }
+
+    // Moves subscriptions left over in redis into the database. Safe to run
+    // more than once; keys are only removed after all of their members are moved.
+    pub async fn migrate_redis_subscriptions(&self) -> Result<usize, Error> {
+        let travel_data = worlds::get_data();
+        let endpoints = travel_data
+            .datacenters
+            .iter()
+            .map(|dc| Endpoint::Datacenter(dc.id))
+            .chain(travel_data.worlds.iter().map(|w| Endpoint::World(w.id)));
+
+        let mut redis = self.redis().clone();
+        let mut migrated = 0;
+        for endpoint in endpoints {
+            let key = endpoint.to_key(self.redis().config())?;
+            let members: Vec<Vec<u8>> = redis.smembers(&key).await?;
+            if members.is_empty() {
+                continue;
+            }
+            for member in members {
+                match Subscriber::from_value(&member) {
+                    Ok(subscriber) => {
+                        if self
+                            .subscribe(endpoint, subscriber, SubscriptionOptions::default())
+                            .await?
+                        {
+                            migrated += 1;
+                        }
+                    }
+                    Err(e) => log::error!(
+                        "Failed to deserialize subscriber: {} (data = {:?})",
+                        e,
+                        member
+                    ),
+                }
+            }
+            let _: () = redis.del(&key).await?;
+        }
+        Ok(migrated)
+    }
+}
+
+fn backoff(attempt: i16) -> time::Duration {
+    let exponent = u32::try_from(attempt.saturating_sub(1))
+        .unwrap_or_default()
+        .min(16);
+    (BASE_BACKOFF * 2i32.saturating_pow(exponent)).min(MAX_BACKOFF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles() {
+        assert_eq!(backoff(1), BASE_BACKOFF);
+        assert_eq!(backoff(2), BASE_BACKOFF * 2);
+        assert_eq!(backoff(4), BASE_BACKOFF * 8);
+    }
+
+    #[test]
+    fn test_backoff_is_capped() {
+        assert_eq!(backoff(MAX_ATTEMPTS), MAX_BACKOFF.min(BASE_BACKOFF * 128));
+        assert_eq!(backoff(i16::MAX), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn test_backoff_without_attempts() {
+        assert_eq!(backoff(0), BASE_BACKOFF);
+        assert_eq!(backoff(-1), BASE_BACKOFF);
+    }
 }
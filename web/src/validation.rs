@@ -0,0 +1,289 @@
+use crate::models::{duty, login};
+use actix_web::{HttpResponse, ResponseError, http::StatusCode};
+use itertools::Itertools;
+use serde::Serialize;
+use thiserror::Error;
+
+// Client clocks and the game's timestamps don't always agree exactly
+const TIME_TOLERANCE: time::Duration = time::Duration::minutes(1);
+
+// Anything longer than these is almost certainly a client that was left open
+const MAX_LOGIN_QUEUE_DURATION: time::Duration = time::Duration::hours(12);
+const MAX_DUTY_QUEUE_DURATION: time::Duration = time::Duration::hours(6);
+
+#[derive(Debug, Error, Serialize)]
+#[error("Invalid {field}: {reason}")]
+pub struct ValidationError {
+    pub field: &'static str,
+    pub reason: &'static str,
+}
+
+impl ValidationError {
+    fn new(field: &'static str, reason: &'static str) -> Self {
+        Self { field, reason }
+    }
+}
+
+impl ResponseError for ValidationError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::BadRequest().json(self)
+    }
+}
+
+// Timestamps must be increasing. Repeats are rejected on their own since
+// they're the primary key alongside the recap id and would fail to insert.
+fn validate_timestamps(
+    field: &'static str,
+    times: impl Iterator<Item = time::OffsetDateTime>,
+) -> Result<(), ValidationError> {
+    for (a, b) in times.tuple_windows() {
+        if b < a {
+            return Err(ValidationError::new(field, "Timestamps are out of order"));
+        }
+        if b == a {
+            return Err(ValidationError::new(field, "Timestamps are duplicated"));
+        }
+    }
+    Ok(())
+}
+
+pub trait Validate {
+    // Rejects clearly malformed data. Plausible but suspicious data is accepted,
+    // and the reason it should be quarantined is returned instead.
+    fn validate(&self) -> Result<Option<&'static str>, ValidationError>;
+}
+
+impl Validate for login::Recap {
+    fn validate(&self) -> Result<Option<&'static str>, ValidationError> {
+        let start = self.start_time.0;
+        let end = self.end_time.0;
+
+        if end < start {
+            return Err(ValidationError::new("end_time", "Ends before it starts"));
+        }
+
+        if self
+            .end_identify_time
+            .is_some_and(|t| t.0 < start - TIME_TOLERANCE)
+        {
+            return Err(ValidationError::new(
+                "end_identify_time",
+                "Identified before the queue started",
+            ));
+        }
+
+        if self.positions.iter().any(|p| p.position < 0) {
+            return Err(ValidationError::new("positions", "Negative position"));
+        }
+
+        validate_timestamps("positions", self.positions.iter().map(|v| v.time.0))?;
+
+        if self
+            .positions
+            .iter()
+            .any(|p| p.time.0 < start - TIME_TOLERANCE || p.time.0 > end + TIME_TOLERANCE)
+        {
+            return Err(ValidationError::new(
+                "positions",
+                "Timestamp is outside of the queue",
+            ));
+        }
+
+        if end - start > MAX_LOGIN_QUEUE_DURATION {
+            return Ok(Some("Queue duration is too long"));
+        }
+
+        if self
+            .positions
+            .iter()
+            .tuple_windows()
+            .any(|(a, b)| b.position > a.position)
+        {
+            return Ok(Some("Queue position increased"));
+        }
+
+        Ok(None)
+    }
+}
+
+impl Validate for duty::Recap {
+    fn validate(&self) -> Result<Option<&'static str>, ValidationError> {
+        let start = self.start_time.0;
+        let end = self.end_time.0;
+
+        if end < start {
+            return Err(ValidationError::new("end_time", "Ends before it starts"));
+        }
+
+        validate_timestamps("updates", self.updates.iter().map(|v| v.time.0))?;
+
+        if self
+            .updates
+            .iter()
+            .any(|u| u.time.0 < start - TIME_TOLERANCE || u.time.0 > end + TIME_TOLERANCE)
+        {
+            return Err(ValidationError::new(
+                "updates",
+                "Timestamp is outside of the queue",
+            ));
+        }
+
+        validate_timestamps("pops", self.pops.iter().map(|v| v.time.0))?;
+
+        if self
+            .pops
+            .iter()
+            .any(|p| p.time.0 < start - TIME_TOLERANCE || p.time.0 > end + TIME_TOLERANCE)
+        {
+            return Err(ValidationError::new(
+                "pops",
+                "Timestamp is outside of the queue",
+            ));
+        }
+
+        if end - start > MAX_DUTY_QUEUE_DURATION {
+            return Ok(Some("Queue duration is too long"));
+        }
+
+        if self.pops.iter().any(|p| {
+            p.in_progress_time
+                .is_some_and(|t| t.0 > p.time.0 + TIME_TOLERANCE)
+        }) {
+            return Ok(Some("Duty began after the pop"));
+        }
+
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn login_recap(positions: &[(&str, i32)]) -> login::Recap {
+        serde_json::from_value(json!({
+            "world_id": 40,
+            "free_trial": false,
+            "successful": true,
+            "reentered": false,
+            "error": null,
+            "start_time": "2024-01-01T00:00:00Z",
+            "end_time": "2024-01-01T01:00:00Z",
+            "end_identify_time": null,
+            "positions": positions
+                .iter()
+                .map(|(time, position)| json!({
+                    "time": time,
+                    "identify_time": null,
+                    "position": position,
+                }))
+                .collect::<Vec<_>>(),
+        }))
+        .unwrap()
+    }
+
+    fn duty_recap(updates: &[&str], pops: &[&str]) -> duty::Recap {
+        let flags = json!({
+            "loot_rule": 0,
+            "is_unrestricted_party": false,
+            "is_min_ilvl": false,
+            "is_silence_echo": false,
+            "is_explorer": false,
+            "is_level_synced": false,
+            "is_limited_leveling": false,
+            "in_progress_party": false,
+        });
+        serde_json::from_value(json!({
+            "queued_roulette": 1,
+            "queued_content": null,
+            "queued_job": 19,
+            "queued_flags": flags,
+            "queued_languages": 2,
+            "world_id": 40,
+            "party": null,
+            "start_time": "2024-01-01T00:00:00Z",
+            "end_time": "2024-01-01T01:00:00Z",
+            "withdraw_message": null,
+            "updates": updates
+                .iter()
+                .map(|time| json!({ "timestamp": time, "is_reserving_server": false }))
+                .collect::<Vec<_>>(),
+            "pops": pops
+                .iter()
+                .map(|time| json!({
+                    "timestamp": time,
+                    "resulting_flags": flags,
+                    "resulting_content": null,
+                    "in_progress_begin_timestamp": null,
+                }))
+                .collect::<Vec<_>>(),
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_login_recap_valid() {
+        let recap = login_recap(&[("2024-01-01T00:00:00Z", 100), ("2024-01-01T00:30:00Z", 50)]);
+        assert_eq!(recap.validate().unwrap(), None);
+    }
+
+    #[test]
+    fn test_login_recap_out_of_order() {
+        let recap = login_recap(&[("2024-01-01T00:30:00Z", 100), ("2024-01-01T00:00:00Z", 50)]);
+        let err = recap.validate().unwrap_err();
+        assert_eq!(err.field, "positions");
+        assert_eq!(err.reason, "Timestamps are out of order");
+    }
+
+    #[test]
+    fn test_login_recap_duplicate_timestamps() {
+        let recap = login_recap(&[("2024-01-01T00:30:00Z", 100), ("2024-01-01T00:30:00Z", 50)]);
+        let err = recap.validate().unwrap_err();
+        assert_eq!(err.field, "positions");
+        assert_eq!(err.reason, "Timestamps are duplicated");
+    }
+
+    #[test]
+    fn test_login_recap_position_increased() {
+        let recap = login_recap(&[("2024-01-01T00:00:00Z", 50), ("2024-01-01T00:30:00Z", 100)]);
+        assert_eq!(recap.validate().unwrap(), Some("Queue position increased"));
+    }
+
+    #[test]
+    fn test_duty_recap_valid() {
+        let recap = duty_recap(
+            &["2024-01-01T00:00:00Z", "2024-01-01T00:10:00Z"],
+            &["2024-01-01T00:20:00Z"],
+        );
+        assert_eq!(recap.validate().unwrap(), None);
+    }
+
+    #[test]
+    fn test_duty_recap_duplicate_update() {
+        let recap = duty_recap(&["2024-01-01T00:10:00Z", "2024-01-01T00:10:00Z"], &[]);
+        let err = recap.validate().unwrap_err();
+        assert_eq!(err.field, "updates");
+        assert_eq!(err.reason, "Timestamps are duplicated");
+    }
+
+    #[test]
+    fn test_duty_recap_pops_out_of_order() {
+        let recap = duty_recap(&[], &["2024-01-01T00:20:00Z", "2024-01-01T00:10:00Z"]);
+        let err = recap.validate().unwrap_err();
+        assert_eq!(err.field, "pops");
+        assert_eq!(err.reason, "Timestamps are out of order");
+    }
+
+    #[test]
+    fn test_duty_recap_outside_queue() {
+        let recap = duty_recap(&["2024-01-01T02:00:00Z"], &[]);
+        let err = recap.validate().unwrap_err();
+        assert_eq!(err.field, "updates");
+        assert_eq!(err.reason, "Timestamp is outside of the queue");
+    }
+}